@@ -0,0 +1,7 @@
+bits 16
+
+mov al, [2555]
+mov ax, [16]
+mov [2554], al
+mov [15], ax
+mov ah, [16]
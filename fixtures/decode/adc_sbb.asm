@@ -0,0 +1,19 @@
+bits 16
+
+adc bx, [bx+si]
+adc bh, [bp + si + 4]
+adc [bp + di + 6], di
+adc si, 2
+adc byte [bx], 34
+adc word [bp + si + 1000], 29
+adc ax, 1000
+adc al, -30
+
+sbb bx, [bx+si]
+sbb bh, [bp + si + 4]
+sbb [bp + di + 6], di
+sbb si, 2
+sbb byte [bx], 34
+sbb word [bx + di], 29
+sbb ax, 1000
+sbb al, 9
@@ -0,0 +1,10 @@
+bits 16
+
+daa
+das
+aaa
+aas
+aam
+aad
+aam 16
+aad 7
@@ -0,0 +1,14 @@
+bits 16
+
+call 4660:22136
+jmp 0:256
+call far [bx + 2]
+jmp far [bp]
+call [si]
+call ax
+jmp [bx + di + 4]
+jmp ax
+ret
+ret 4
+retf
+retf 8
@@ -0,0 +1,10 @@
+bits 16
+
+mov ax, 1
+; c1 is a hole in the opcode map and lea can't take a register, neither decodes
+db 0xc1
+db 0x8d, 0xc0
+mov bx, 2
+; a table of data in between the code
+db 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6, 0xd6
+mov cx, 3
@@ -0,0 +1,21 @@
+bits 16
+
+xchg ax, cx
+xchg ax, di
+xchg cx, [bx]
+xchg al, [bp + 4]
+xchg dl, [1000]
+xchg [bx + si], bx
+xchg bx, dx
+xchg cl, ah
+
+xlat
+lahf
+sahf
+cbw
+cwd
+
+lea bx, [bp + si + 4]
+lea di, [1000]
+lds si, [bx]
+les di, [bp + 2]
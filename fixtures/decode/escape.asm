@@ -0,0 +1,7 @@
+bits 16
+
+; esc has no mnemonic of its own, it's spelled as whatever the coprocessor makes of it
+db 0xd8, 0xc1
+db 0xd9, 0x07
+db 0xdf, 0x7e, 0x08
+db 0xdd, 0x16, 0xe8, 0x03
@@ -0,0 +1,11 @@
+bits 16
+
+in al, 200
+in ax, 96
+in al, dx
+in ax, dx
+out 67, al
+out 200, ax
+out dx, al
+out dx, ax
+in al, 0
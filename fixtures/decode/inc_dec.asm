@@ -0,0 +1,23 @@
+bits 16
+
+inc ax
+inc si
+dec cx
+dec di
+inc bl
+dec ah
+inc dx
+dec bp
+inc byte [bx]
+dec word [bp + si + 4]
+inc word [1000]
+
+call bx
+call [bx]
+call [bp + si + 4]
+jmp di
+jmp [bp + 4]
+call far [bx]
+jmp far [di + 2]
+push word [bx]
+push word [bp + 2]
@@ -0,0 +1,7 @@
+bits 16
+
+int 33
+int3
+into
+iret
+int 255
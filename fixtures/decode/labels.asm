@@ -0,0 +1,29 @@
+bits 16
+
+mov cx, 10
+mov ax, 0
+outer:
+add ax, cx
+test ax, 1
+jz even
+inc bx
+jmp short next
+even:
+dec bx
+next:
+loop outer
+
+call square
+jcxz skip
+db 0xc1, 0xc1
+skip:
+jmp near done
+
+square:
+mul ax
+ret
+
+done:
+cmp ax, 100
+jb outer
+jmp near done
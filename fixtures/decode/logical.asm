@@ -0,0 +1,40 @@
+bits 16
+
+and bx, [bx+si]
+and bx, [bp]
+and si, 2
+and cx, 1000
+and bh, [bp + si + 4]
+and [bp + di + 6], di
+and byte [bx], 34
+and word [bp + si + 1000], 29
+and ax, 1000
+and al, 9
+
+or bx, [bx+si]
+or si, 2
+or [bx + 2], cx
+or byte [bx], 34
+or ax, 1000
+or al, 9
+
+xor bx, [bx+si]
+xor si, 2
+xor [bp + si + 4], bh
+xor word [4834], 29
+xor ax, 1000
+xor al, 9
+
+test bx, cx
+test [bx + si], dx
+test bh, [bp + 4]
+test byte [bx], 34
+test word [bp + di + 1000], 29
+test si, 2
+test ax, 1000
+test al, 9
+
+not ax
+not bl
+not word [bx]
+not byte [bp + di + 2]
@@ -0,0 +1,18 @@
+bits 16
+
+mul bx
+mul cl
+imul ax
+imul dh
+div si
+div bl
+idiv di
+idiv ah
+neg ax
+neg bl
+mul word [bx]
+imul byte [bp + si + 4]
+div word [bp]
+idiv byte [di + 1000]
+neg word [bx + 2]
+neg byte [4834]
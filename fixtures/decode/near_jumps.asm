@@ -0,0 +1,10 @@
+bits 16
+
+start:
+call start
+call done
+jmp short start
+jmp near start
+jmp near done
+jmp short done
+done:
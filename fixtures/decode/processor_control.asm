@@ -0,0 +1,14 @@
+bits 16
+
+clc
+stc
+cmc
+cld
+std
+cli
+sti
+hlt
+wait
+nop
+lock xchg [bx], ax
+lock inc word [si + 4]
@@ -0,0 +1,17 @@
+bits 16
+
+mov ds, ax
+mov es, bx
+mov ss, [bp + 2]
+mov ds, [bx + si]
+mov ax, cs
+mov [bx], es
+mov [4000], ss
+
+mov ax, [es:bx]
+mov [cs:bp + 4], cx
+mov bl, [ss:bx + di]
+mov [ds:bp - 2], al
+add word [es:bp + 2], 7
+inc byte [es:1000]
+mov word [ss:bx], 300
@@ -0,0 +1,24 @@
+bits 16
+
+shl ax, 1
+shl bl, 1
+shl cx, cl
+shl dh, cl
+sal si, 1
+shr di, 1
+shr ah, cl
+sar bp, 1
+sar dx, cl
+rol ax, 1
+rol bh, cl
+ror sp, 1
+ror cl, cl
+rcl bx, 1
+rcl ax, cl
+rcr dl, 1
+rcr si, cl
+shl byte [bx], 1
+shr word [bp + 4], cl
+sar byte [bx + si + 1000], cl
+rol word [di], 1
+rcr byte [bp], cl
@@ -0,0 +1,6 @@
+bits 16
+
+; the last instruction is a single byte, with nothing after it to read as a second one
+mov ax, 1
+rep movsb
+hlt
@@ -0,0 +1,21 @@
+bits 16
+
+push ax
+push si
+push word [bx]
+push word [bp + si + 4]
+push es
+push cs
+push ss
+push ds
+
+pushf
+popf
+
+pop di
+pop bp
+pop es
+pop ss
+pop ds
+pop word [bx + 2]
+pop word [1000]
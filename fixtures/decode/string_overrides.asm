@@ -0,0 +1,14 @@
+bits 16
+
+es movsb
+cs lodsw
+ss cmpsb
+ds movsw
+
+rep es movsw
+repne cs cmpsb
+repe ss cmpsw
+rep ds lodsb
+
+cs xlat
+es xlat
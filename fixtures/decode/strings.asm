@@ -0,0 +1,23 @@
+bits 16
+
+movsb
+movsw
+cmpsb
+cmpsw
+scasb
+scasw
+lodsb
+lodsw
+stosb
+stosw
+
+mov cx, 8
+rep movsb
+rep movsw
+repe cmpsb
+repne cmpsw
+repe scasw
+repne scasb
+rep lodsb
+rep stosw
+mov cx, 0
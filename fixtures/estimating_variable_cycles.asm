@@ -0,0 +1,25 @@
+bits 16
+
+; these take however long the data makes them, the estimate is the least they can take
+mov ax, 30
+mov cx, 3
+mul cx
+div cl
+shl ax, cl
+shl word [bx], 1
+rep movsb
+cmp ax, 240
+jne done
+mov cx, 1
+loop done
+lock inc word [bx + si]
+push ax
+pop word [1000]
+call routine
+jmp done
+
+routine:
+ret
+
+done:
+hlt
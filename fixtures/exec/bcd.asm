@@ -0,0 +1,41 @@
+bits 16
+
+; packed bcd, 38 + 45 = 83
+mov al, 0x38
+add al, 0x45
+daa
+mov bl, al
+
+; and back again, 83 - 45 = 38
+sub al, 0x45
+das
+mov bh, al
+
+; 99 + 1 carries out of the byte
+mov al, 0x99
+add al, 1
+daa
+mov cl, al
+
+; unpacked bcd, 9 + 5 = 14
+mov ax, 9
+add al, 5
+aaa
+mov dx, ax
+
+; 14 - 5 = 9
+sub al, 5
+aas
+mov si, ax
+
+; 7 * 8 = 56, split into digits and joined again
+mov al, 7
+mov ch, 8
+mul ch
+aam
+mov di, ax
+aad
+
+; the base can be anything
+mov al, 0x4f
+aam 16
@@ -0,0 +1,47 @@
+bits 16
+
+mov ax, 4660
+mov al, 5
+mov ah, 7
+add ax, 256
+mov bl, 200
+add bl, 100
+mov bh, bl
+sub bh, 45
+mov cx, bx
+mov dl, ch
+mov dh, cl
+
+; byte arithmetic sets the flags from bit 7
+mov al, 127
+add al, 1
+mov ah, 0
+sub ah, 1
+cmp ah, 255
+
+; byte memory accesses only touch a single byte
+mov word [1000], 65535
+mov byte [1000], 18
+mov [1001], ah
+mov cl, [1000]
+mov al, [1001]
+mov [1002], al
+mov dx, word [1000]
+and byte [1001], 15
+or [1000], dl
+mov si, word [1000]
+
+; byte shifts, rotates and multiplies
+mov bl, 129
+shl bl, 1
+rol bl, 1
+mov cl, 4
+ror bh, cl
+mov al, 12
+mov ch, 10
+mul ch
+div ch
+inc cl
+dec bh
+neg ch
+not bl
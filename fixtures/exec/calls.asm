@@ -0,0 +1,54 @@
+bits 16
+
+mov sp, 1024
+
+call add_numbers
+mov cx, ax
+
+; ret 4 also pops the two arguments pushed here
+mov bx, 3
+push bx
+mov bx, 4
+push bx
+call sum_args
+mov dx, ax
+
+; the program is loaded at segment 2048, so segment 2049 starts 16 bytes in and the same code
+; is found 16 bytes earlier
+call 2049:far_routine-16
+mov si, ax
+
+mov word [2000], far_routine
+mov [2002], cs
+call far [2000]
+mov di, ax
+
+jmp near over
+mov di, 1
+over:
+mov bx, indirect_target
+jmp bx
+mov di, 2
+indirect_target:
+mov word [2000], the_end
+jmp far [2000]
+mov di, 3
+
+add_numbers:
+mov ax, 5
+add ax, 7
+ret
+
+sum_args:
+mov bp, sp
+mov ax, [bp + 2]
+add ax, [bp + 4]
+ret 4
+
+far_routine:
+mov ax, 100
+add ax, cx
+retf
+
+the_end:
+mov bp, 1
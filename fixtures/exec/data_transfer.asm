@@ -0,0 +1,37 @@
+bits 16
+
+mov ax, 1
+mov cx, 2
+xchg ax, cx
+mov word [1000], 3
+xchg cx, [1000]
+mov dx, [1000]
+
+; a table of squares to look up
+mov byte [2000], 0
+mov byte [2001], 1
+mov byte [2002], 4
+mov byte [2003], 9
+mov bx, 2000
+mov al, 3
+xlat
+
+mov bp, 100
+mov si, 20
+lea di, [bp + si + 4]
+
+; a far pointer to 4096:5678
+mov word [3000], 5678
+mov word [3002], 4096
+les di, [3000]
+lds si, [3000]
+
+mov ax, 65535
+add ax, 1
+lahf
+mov ah, 128
+sahf
+
+mov al, 200
+cbw
+cwd
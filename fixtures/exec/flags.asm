@@ -0,0 +1,21 @@
+bits 16
+
+mov ax, 65535
+add ax, 1
+mov bx, 32767
+add bx, 1
+
+; borrowing ripples through sbb, carries through adc
+mov cx, 5
+sub cx, 6
+mov dx, 0
+sbb dx, 0
+add cx, 1
+adc dx, 0
+
+; byte comparisons look at bit 7 and the low byte only
+mov ax, 200
+cmp al, 100
+cmp al, 201
+cmp ax, 201
+cmp ax, 100
@@ -0,0 +1,13 @@
+bits 16
+
+; nothing is attached, so every read comes back with all bits set
+in al, 96
+mov bl, al
+mov dx, 1016
+in ax, dx
+mov cx, ax
+
+mov al, 72
+out dx, al
+mov ax, 4660
+out 128, ax
@@ -0,0 +1,33 @@
+bits 16
+
+mov sp, 512
+
+; inc and dec must leave the carry flag alone
+mov bx, 32769
+shl bx, 1
+mov ax, 65535
+inc ax
+dec ax
+
+mov cx, 32767
+inc cx
+dec cx
+
+mov word [1000], 5
+inc word [1000]
+dec word [1000]
+dec word [1000]
+mov si, word [1000]
+
+mov bx, subroutine
+call bx
+mov dx, 1
+
+subroutine:
+mov word [1002], skip
+jmp [1002]
+mov dx, 2
+
+skip:
+push word [1000]
+mov di, 3
@@ -0,0 +1,36 @@
+bits 16
+
+; the vector table is at the start of memory, ds is still 0 there
+mov sp, 1024
+mov word [12], breakpoint
+mov [14], cs
+mov word [16], overflow
+mov [18], cs
+mov word [132], service
+mov [134], cs
+
+mov ax, 1
+int 33
+int3
+mov bx, 32767
+add bx, 1
+into
+mov bx, 0
+add bx, 1
+into
+jmp done
+
+service:
+add ax, 10
+iret
+
+breakpoint:
+add ax, 100
+iret
+
+overflow:
+add ax, 1000
+iret
+
+done:
+mov cx, ax
@@ -0,0 +1,19 @@
+bits 16
+
+mov ax, 61680
+mov bx, 3855
+and ax, bx
+or ax, bx
+xor ax, 255
+test ax, 16
+not bx
+
+mov word [1000], 4660
+and word [1000], 255
+or [1000], bx
+mov dx, word [1000]
+not word [1000]
+mov si, word [1000]
+
+xor cx, cx
+test bx, bx
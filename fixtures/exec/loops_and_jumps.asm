@@ -0,0 +1,70 @@
+bits 16
+
+; count down with loop
+mov cx, 4
+mov bx, 0
+loop_start:
+	add bx, 2
+	loop loop_start
+
+; loopnz stops as soon as a result is zero
+mov cx, 10
+mov ax, 3
+loopnz_start:
+	sub ax, 1
+	loopnz loopnz_start
+
+; loopz keeps going while results are zero
+mov cx, 3
+mov dx, 0
+loopz_start:
+	add dx, 0
+	loopz loopz_start
+
+jcxz skip_mov_si
+mov si, 1
+skip_mov_si:
+
+; signed comparisons
+mov ax, 5
+cmp ax, 7
+jl is_less
+mov si, 2
+is_less:
+jg is_greater
+mov di, 1
+is_greater:
+jle is_less_or_equal
+mov di, 2
+is_less_or_equal:
+
+; unsigned comparisons on the carry flag
+mov bx, 32768
+shl bx, 1
+jb is_below
+mov si, 3
+is_below:
+jbe is_below_or_equal
+mov si, 4
+is_below_or_equal:
+ja is_above
+jnb is_above
+mov di, 3
+is_above:
+
+; overflow, sign and parity
+mov cx, 32767
+inc cx
+jo overflowed
+mov si, 5
+overflowed:
+js signed
+mov si, 6
+signed:
+jnp odd_parity
+mov si, 7
+odd_parity:
+jns not_signed
+mov di, 4
+not_signed:
+mov bp, 1
@@ -0,0 +1,33 @@
+bits 16
+
+; byte stores must only touch the byte they address
+mov word [1000], 65535
+mov byte [1000], 0
+mov byte [1001], 18
+mov si, word [1000]
+
+mov word [1002], 1
+not byte [1002]
+neg byte [1003]
+mov di, word [1002]
+
+mov byte [1004], 255
+inc byte [1004]
+mov word [1006], 255
+inc word [1006]
+mov bx, 1004
+mov cx, word [bx]
+mov dx, word [bx + 2]
+
+mov byte [1008], 129
+shl byte [1008], 1
+mov word [1010], 129
+shl word [1010], 1
+mov bp, word [1008]
+mov sp, word [1010]
+
+mov al, 200
+mov byte [1012], 10
+mul byte [1012]
+mov word [1012], 9
+div byte [1012]
@@ -0,0 +1,36 @@
+bits 16
+
+; Point the divide error vector at our handler
+mov word [0], divide_error
+mov [2], cs
+mov sp, 512
+
+mov ax, 1000
+mov bx, 7
+mul bx
+mov ax, -1
+imul bx
+mov ax, 300
+mov cx, 250
+mul cx
+
+mov dx, 0
+mov ax, 1000
+div bx
+mov ax, -1000
+mov dx, -1
+idiv bx
+neg ax
+
+mov ax, 1003
+mov cx, 10
+div cl
+mov ax, 200
+mul cl
+
+mov cx, 0
+div cx
+mov ax, 1
+
+divide_error:
+mov bx, 2
@@ -0,0 +1,5 @@
+bits 16
+
+; inc cx and hlt, written to the start of segment 1024 and run from there
+mov word [16384], 62529
+jmp 1024:0
@@ -0,0 +1,6 @@
+bits 16
+
+; jumping past the last instruction stops the program, rather than running whatever is there
+mov ax, 1
+jmp $+256
+mov ax, 2
@@ -0,0 +1,19 @@
+bits 16
+
+stc
+cmc
+stc
+std
+sti
+cld
+cli
+clc
+nop
+wait
+mov ax, 1
+lock xchg [1000], ax
+mov bx, [1000]
+hlt
+
+; never reached
+mov cx, 1
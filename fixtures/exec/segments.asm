@@ -0,0 +1,26 @@
+bits 16
+
+; the data segment starts at 65536, past the segment the program lives in
+mov ax, 4096
+mov ds, ax
+mov word [8], 4660
+mov bx, [8]
+
+mov ax, 8192
+mov es, ax
+mov word [es:8], 22136
+mov cx, [es:8]
+mov dx, [8]
+
+; bp based addresses go through the stack segment
+mov ss, ax
+mov bp, 8
+mov si, [bp]
+mov di, [bp + si]
+mov [ds:bp + 2], bx
+mov di, [10]
+
+mov ax, ds
+mov [bp + 2], ax
+mov sp, [es:10]
+mov es, sp
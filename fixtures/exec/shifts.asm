@@ -0,0 +1,25 @@
+bits 16
+
+mov ax, 1
+shl ax, 1
+mov cx, 3
+shl ax, cl
+
+mov bx, 32769
+shl bx, 1
+rcl bx, 1
+rcr bx, 1
+rcr bx, 1
+
+mov dx, 65280
+sar dx, cl
+shr dx, 1
+shr dx, cl
+
+ror ax, 1
+ror ax, cl
+rol ax, 1
+
+mov word [1000], 3
+shl word [1000], cl
+mov si, word [1000]
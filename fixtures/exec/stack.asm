@@ -0,0 +1,34 @@
+bits 16
+
+; the stack lives in a segment of its own
+mov ax, 4096
+mov ss, ax
+mov sp, 256
+
+mov bx, 4660
+mov cx, 22136
+push bx
+push cx
+pop dx
+pop si
+
+mov word [1000], 291
+push word [1000]
+pop word [1002]
+mov di, [1002]
+
+push ss
+pop es
+push sp
+pop bp
+
+; the flags make the round trip through the stack too
+mov ax, 65535
+add ax, 1
+pushf
+mov ax, 1
+add ax, 1
+popf
+pushf
+pop ax
+mov bx, ax
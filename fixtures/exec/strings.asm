@@ -0,0 +1,51 @@
+bits 16
+
+mov word [1000], 1
+mov word [1002], 2
+mov word [1004], 3
+
+; copy three words into the extra segment
+mov ax, 4096
+mov es, ax
+mov si, 1000
+mov di, 2000
+mov cx, 3
+rep movsw
+mov bx, [es:2004]
+
+; fill four bytes with 7, followed by a zero
+mov al, 7
+mov di, 3000
+mov cx, 4
+rep stosb
+mov dx, [es:3002]
+
+; look for the zero after them
+mov al, 0
+mov di, 3000
+mov cx, 10
+repne scasb
+
+; the copy is identical to the original
+mov ax, 0
+mov es, ax
+mov si, 1000
+mov di, 1000
+mov cx, 6
+repe cmpsb
+
+mov si, 1002
+lodsw
+mov bp, ax
+
+; with the direction flag set, copy backwards
+pushf
+pop ax
+or ax, 1024
+push ax
+popf
+mov si, 1004
+mov di, 4004
+mov cx, 3
+rep movsw
+mov sp, [4000]
@@ -0,0 +1,1534 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    adc::Adc, add::Add, and::And, call::Call, cmp::Cmp, dec::Dec, encode::encode, inc::Inc,
+    input::In, jmp::Jmp, jump::J, mov::Mov, or::Or, output::Out, pop::Pop, push::Push, ret::Ret,
+    sbb::Sbb, shift::Shift, sub::Sub, test::Test, xchg::Xchg, xor::Xor, EffectiveAddressCalc,
+    Instruction, Opcode, Operand, Register, Repeat,
+};
+
+/// How many times the program gets laid out before giving up on the labels settling.
+const MAX_PASSES: usize = 32;
+
+/// Why a line of source couldn't be assembled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AsmErrorReason {
+    /// The line doesn't parse, like an unclosed bracket or a missing operand.
+    Syntax,
+    UnknownMnemonic,
+    /// An operand names a label that isn't defined anywhere.
+    UnknownLabel,
+    DuplicateLabel,
+    /// The operands don't fit any form of the instruction, like two memory operands for mov.
+    InvalidOperands,
+    /// Nothing says whether a memory operand is a byte or a word, like in `inc [bx]`.
+    SizeNotSpecified,
+    /// A value doesn't fit the byte or word it goes in, or a short jump can't reach its target.
+    OutOfRange,
+    /// The label defined on this line kept moving from one pass to the next.
+    Unsettled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    /// Line number in the source, starting at 1.
+    pub line: usize,
+    /// The line as written, without its comment.
+    pub source: String,
+    pub reason: AsmErrorReason,
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            AsmErrorReason::Syntax => "syntax error",
+            AsmErrorReason::UnknownMnemonic => "unknown mnemonic",
+            AsmErrorReason::UnknownLabel => "unknown label",
+            AsmErrorReason::DuplicateLabel => "duplicate label",
+            AsmErrorReason::InvalidOperands => "invalid operands",
+            AsmErrorReason::SizeNotSpecified => "operation size not specified",
+            AsmErrorReason::OutOfRange => "value out of range",
+            AsmErrorReason::Unsettled => "label address doesn't settle",
+        };
+
+        write!(f, "line {}: {} in `{}`", self.line, reason, self.source)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// Assembles the subset of NASM syntax a flat 16-bit program needs into machine code, picking
+/// the same encodings NASM does so the bytes come out identical.
+///
+/// Besides instructions there's `bits 16`, labels, `db` and `dw`, and constant expressions
+/// made of numbers, labels and `$` with `+`, `-`, `*` and parentheses.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    Assembly::new(source).map(|assembly| assembly.binary)
+}
+
+/// An assembled program along with the source lines its bytes came from.
+pub struct Assembly {
+    pub binary: Vec<u8>,
+    /// Every line that assembled to at least one byte, in order.
+    pub lines: Vec<SourceLine>,
+}
+
+pub struct SourceLine {
+    /// Offset into the binary of the first byte of the line.
+    pub offset: usize,
+    /// Line number in the source, starting at 1.
+    pub number: usize,
+    /// The line as written, without its comment.
+    pub text: String,
+}
+
+impl Assembly {
+    /// Like [`assemble`], keeping track of which line each byte came from.
+    pub fn new(source: &str) -> Result<Self, AsmError> {
+        let statements = source
+            .lines()
+            .enumerate()
+            .map(|(n, text)| Statement::parse(n + 1, text))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut assembler = Assembler {
+            defined: HashSet::new(),
+            previous: HashMap::new(),
+            labels: HashMap::new(),
+            near_jumps: HashSet::new(),
+            strict: false,
+        };
+
+        for statement in &statements {
+            if let Some(label) = &statement.label {
+                if !assembler.defined.insert(label.clone()) {
+                    return Err(statement.error(AsmErrorReason::DuplicateLabel));
+                }
+            }
+        }
+
+        // what size an instruction is can depend on where a label is and the other way
+        // around, so the program is laid out again until the labels stop moving
+        let mut passes = 0;
+        loop {
+            let assembly = assembler.pass(&statements)?;
+            let settled = assembler.labels == assembler.previous;
+
+            if assembler.strict {
+                if settled {
+                    return Ok(assembly);
+                }
+
+                let moved = statements
+                    .iter()
+                    .find(|s| {
+                        s.label
+                            .as_ref()
+                            .is_some_and(|l| assembler.labels.get(l) != assembler.previous.get(l))
+                    })
+                    .expect("a label to have moved");
+                return Err(moved.error(AsmErrorReason::Unsettled));
+            }
+
+            passes += 1;
+            // one more pass, now complaining about anything that doesn't fit
+            if settled || passes == MAX_PASSES {
+                assembler.strict = true;
+            }
+
+            assembler.previous = std::mem::take(&mut assembler.labels);
+        }
+    }
+
+    /// The line that assembled to the byte at `offset`, if there is one.
+    pub fn line_at(&self, offset: usize) -> Option<&SourceLine> {
+        if offset >= self.binary.len() {
+            return None;
+        }
+
+        let after = self.lines.partition_point(|line| line.offset <= offset);
+        self.lines.get(after.checked_sub(1)?)
+    }
+}
+
+struct Assembler {
+    /// Every label in the program, to tell a forward reference apart from a typo.
+    defined: HashSet<String>,
+    /// Where each label ended up in the previous pass, for forward references.
+    previous: HashMap<String, u16>,
+    /// Where each label is in the current pass, as far as it got.
+    labels: HashMap<String, u16>,
+    /// Statements with a jmp that had to be near in some pass. They stay near, so the layout
+    /// only ever grows and the passes come to an end.
+    near_jumps: HashSet<usize>,
+    /// Whether to report values that don't fit, which only makes sense once the labels have
+    /// settled.
+    strict: bool,
+}
+
+impl Assembler {
+    fn pass(&mut self, statements: &[Statement]) -> Result<Assembly, AsmError> {
+        let mut bytes = Vec::new();
+        let mut lines = Vec::new();
+
+        for (index, statement) in statements.iter().enumerate() {
+            let here = bytes.len() as u16;
+
+            if let Some(label) = &statement.label {
+                self.labels.insert(label.clone(), here);
+            }
+
+            let encoded = match &statement.kind {
+                Kind::Empty => Ok(Vec::new()),
+                Kind::Data { wide, values } => self.data(values, *wide, here),
+                Kind::Prefixes(prefixes) => Ok(prefixes.iter().map(Prefix::byte).collect()),
+                Kind::Instruction {
+                    prefixes,
+                    mnemonic,
+                    args,
+                } => self.instruction(index, here, prefixes, mnemonic, args),
+            };
+
+            let encoded = encoded.map_err(|reason| statement.error(reason))?;
+            if !encoded.is_empty() {
+                lines.push(SourceLine {
+                    offset: bytes.len(),
+                    number: statement.line,
+                    text: statement.source.clone(),
+                });
+            }
+
+            bytes.extend(encoded);
+        }
+
+        Ok(Assembly {
+            binary: bytes,
+            lines,
+        })
+    }
+
+    fn value(&self, expr: &Expr, here: u16) -> Result<i64, AsmErrorReason> {
+        let value = match expr {
+            Expr::Number(n) => *n,
+            Expr::Here => here as i64,
+            Expr::Label(name) => {
+                if !self.defined.contains(name) {
+                    return Err(AsmErrorReason::UnknownLabel);
+                }
+
+                // a label that hasn't been reached yet in the first pass is taken to be right
+                // here, which makes jumps to it short until proven otherwise
+                let address = self.labels.get(name).or_else(|| self.previous.get(name));
+                *address.unwrap_or(&here) as i64
+            }
+            Expr::Negate(e) => self.value(e, here)?.wrapping_neg(),
+            Expr::Binary(a, op, b) => {
+                let (a, b) = (self.value(a, here)?, self.value(b, here)?);
+                match op {
+                    '+' => a.wrapping_add(b),
+                    '-' => a.wrapping_sub(b),
+                    _ => a.wrapping_mul(b),
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Truncates `value` to a byte or a word. Either a signed or an unsigned value fits, the
+    /// way NASM lets both through.
+    fn fit(&self, value: i64, wide: bool) -> Result<u16, AsmErrorReason> {
+        let range = if wide { -0x8000..=0xffff } else { -0x80..=0xff };
+        if self.strict && !range.contains(&value) {
+            return Err(AsmErrorReason::OutOfRange);
+        }
+
+        Ok(if wide {
+            value as u16
+        } else {
+            value as u8 as u16
+        })
+    }
+
+    fn immediate(&self, expr: &Expr, wide: bool, here: u16) -> Result<Operand, AsmErrorReason> {
+        Ok(Operand::Immediate(self.fit(self.value(expr, here)?, wide)?))
+    }
+
+    fn operand(&self, arg: &Arg, wide: bool, here: u16) -> Result<Operand, AsmErrorReason> {
+        match arg {
+            Arg::Register(r) => Ok(Operand::Register(*r)),
+            Arg::Memory {
+                registers,
+                displacement,
+                ..
+            } => self.memory(registers, displacement.as_ref(), here),
+            Arg::Immediate { value, .. } => self.immediate(value, wide, here),
+            Arg::FarPointer(..) => Err(AsmErrorReason::InvalidOperands),
+        }
+    }
+
+    fn memory(
+        &self,
+        registers: &[Register],
+        displacement: Option<&Expr>,
+        here: u16,
+    ) -> Result<Operand, AsmErrorReason> {
+        use EffectiveAddressCalc as E;
+        use Register as R;
+
+        let displacement = match displacement {
+            Some(expr) => self.fit(self.value(expr, here)?, true)?,
+            None => 0,
+        };
+
+        let eac = match registers {
+            [] => E::DirectAddress(displacement),
+            [r @ (R::BX | R::BP | R::SI | R::DI)] => match displacement {
+                0 => E::SingleReg(*r),
+                d => E::SingleRegPlus(*r, d as i16),
+            },
+            [base @ (R::BX | R::BP), index @ (R::SI | R::DI)]
+            | [index @ (R::SI | R::DI), base @ (R::BX | R::BP)] => match displacement {
+                0 => E::Plus(*base, *index),
+                d => E::PlusConstant(*base, *index, d as i16),
+            },
+            _ => return Err(AsmErrorReason::InvalidOperands),
+        };
+
+        Ok(Operand::MemoryAddress(eac))
+    }
+
+    /// The increment from the end of a jump of `length` bytes to `target`.
+    fn increment(&self, target: &Expr, here: u16, length: u16) -> Result<i64, AsmErrorReason> {
+        Ok(self.value(target, here)? - (here as i64 + length as i64))
+    }
+
+    fn short_increment(
+        &self,
+        target: &Expr,
+        here: u16,
+        length: u16,
+    ) -> Result<Operand, AsmErrorReason> {
+        let increment = self.increment(target, here, length)?;
+        if self.strict && i8::try_from(increment).is_err() {
+            return Err(AsmErrorReason::OutOfRange);
+        }
+
+        Ok(Operand::InstPtrIncrement(increment as i8))
+    }
+
+    fn data(&self, values: &[Datum], wide: bool, here: u16) -> Result<Vec<u8>, AsmErrorReason> {
+        let mut bytes = Vec::new();
+
+        for datum in values {
+            let expr = match datum {
+                Datum::Expr(expr) => expr,
+                Datum::String(string) => {
+                    bytes.extend(string);
+                    if wide && string.len() % 2 == 1 {
+                        bytes.push(0);
+                    }
+                    continue;
+                }
+            };
+
+            let value = self.fit(self.value(expr, here)?, wide)?;
+
+            if wide {
+                bytes.extend(value.to_le_bytes());
+            } else {
+                bytes.push(value as u8);
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn instruction(
+        &mut self,
+        index: usize,
+        here: u16,
+        prefixes: &[Prefix],
+        mnemonic: &str,
+        args: &[Arg],
+    ) -> Result<Vec<u8>, AsmErrorReason> {
+        use AsmErrorReason::InvalidOperands;
+
+        // jumps are relative to the end of the whole instruction, prefixes and all
+        let prefix_length = prefixes.len() as u16;
+
+        let (opcode, wide, destination, source) = match (mnemonic, args) {
+            ("mov", [destination, source]) => {
+                let wide = width(&[destination, source])?;
+
+                let opcode = match (destination, source) {
+                    (Arg::Register(r), _) if is_segment(r) => Mov::RegOrMemToSeg,
+                    (_, Arg::Register(r)) if is_segment(r) => Mov::SegToRegOrMem,
+                    (Arg::Register(_), Arg::Immediate { .. }) => Mov::ImmToReg,
+                    (_, Arg::Immediate { .. }) => Mov::ImmToRegOrMem,
+                    (a, m) if is_accumulator(a) && is_direct_address(m) => Mov::MemToAcc,
+                    (m, a) if is_accumulator(a) && is_direct_address(m) => Mov::AccToMem,
+                    _ => Mov::RM,
+                };
+
+                (
+                    Opcode::Mov(opcode),
+                    wide,
+                    self.operand(destination, wide, here)?,
+                    Some(self.operand(source, wide, here)?),
+                )
+            }
+            (
+                "add" | "or" | "adc" | "sbb" | "and" | "sub" | "xor" | "cmp" | "test",
+                [destination, source],
+            ) => {
+                let wide = width(&[destination, source])?;
+                let source = self.operand(source, wide, here)?;
+
+                let (rm, imm_to_reg_or_memory, imm_to_acc) = match mnemonic {
+                    "add" => (
+                        Opcode::Add(Add::RM),
+                        Opcode::Add(Add::ImmToRegOrMem),
+                        Opcode::Add(Add::ImmToAcc),
+                    ),
+                    "or" => (
+                        Opcode::Or(Or::RM),
+                        Opcode::Or(Or::ImmToRegOrMem),
+                        Opcode::Or(Or::ImmToAcc),
+                    ),
+                    "adc" => (
+                        Opcode::Adc(Adc::RM),
+                        Opcode::Adc(Adc::ImmToRegOrMem),
+                        Opcode::Adc(Adc::ImmToAcc),
+                    ),
+                    "sbb" => (
+                        Opcode::Sbb(Sbb::RM),
+                        Opcode::Sbb(Sbb::ImmToRegOrMem),
+                        Opcode::Sbb(Sbb::ImmToAcc),
+                    ),
+                    "and" => (
+                        Opcode::And(And::RM),
+                        Opcode::And(And::ImmToRegOrMem),
+                        Opcode::And(And::ImmToAcc),
+                    ),
+                    "sub" => (
+                        Opcode::Sub(Sub::RM),
+                        Opcode::Sub(Sub::ImmToRegOrMem),
+                        Opcode::Sub(Sub::ImmToAcc),
+                    ),
+                    "xor" => (
+                        Opcode::Xor(Xor::RM),
+                        Opcode::Xor(Xor::ImmToRegOrMem),
+                        Opcode::Xor(Xor::ImmToAcc),
+                    ),
+                    "cmp" => (
+                        Opcode::Cmp(Cmp::RM),
+                        Opcode::Cmp(Cmp::ImmToRegOrMem),
+                        Opcode::Cmp(Cmp::ImmToAcc),
+                    ),
+                    _ => (
+                        Opcode::Test(Test::RM),
+                        Opcode::Test(Test::ImmToRegOrMem),
+                        Opcode::Test(Test::ImmToAcc),
+                    ),
+                };
+
+                match source {
+                    Operand::Immediate(value) => {
+                        // a sign extended byte beats the accumulator form, test has none though
+                        let sign_extends =
+                            wide && mnemonic != "test" && i8::try_from(value as i16).is_ok();
+
+                        let opcode = if is_accumulator(destination) && !sign_extends {
+                            imm_to_acc
+                        } else {
+                            imm_to_reg_or_memory
+                        };
+
+                        (
+                            opcode,
+                            wide,
+                            self.operand(destination, wide, here)?,
+                            Some(source),
+                        )
+                    }
+                    // test has no D bit, the register always goes in the source
+                    Operand::MemoryAddress(_) if mnemonic == "test" => (
+                        rm,
+                        wide,
+                        source,
+                        Some(self.operand(destination, wide, here)?),
+                    ),
+                    _ => (
+                        rm,
+                        wide,
+                        self.operand(destination, wide, here)?,
+                        Some(source),
+                    ),
+                }
+            }
+            ("xchg", [destination, source]) => {
+                let wide = width(&[destination, source])?;
+                let mut destination = self.operand(destination, wide, here)?;
+                let mut source = self.operand(source, wide, here)?;
+
+                match (&destination, &source) {
+                    (Operand::Register(Register::AX), Operand::Register(_)) => (
+                        Opcode::Xchg(Xchg::RegWithAcc),
+                        wide,
+                        destination,
+                        Some(source),
+                    ),
+                    (Operand::Register(_), Operand::Register(Register::AX)) => (
+                        Opcode::Xchg(Xchg::RegWithAcc),
+                        wide,
+                        source,
+                        Some(destination),
+                    ),
+                    _ => {
+                        // xchg has no D bit either, the register goes in REG. Between two
+                        // registers NASM puts the first one there.
+                        if let Operand::MemoryAddress(_) = destination {
+                            std::mem::swap(&mut destination, &mut source);
+                        }
+
+                        (Opcode::Xchg(Xchg::RM), wide, destination, Some(source))
+                    }
+                }
+            }
+            ("not" | "neg" | "mul" | "imul" | "div" | "idiv", [destination]) => {
+                let wide = width(&[destination])?;
+                let opcode = match mnemonic {
+                    "not" => Opcode::Not,
+                    "neg" => Opcode::Neg,
+                    "mul" => Opcode::Mul,
+                    "imul" => Opcode::Imul,
+                    "div" => Opcode::Div,
+                    _ => Opcode::Idiv,
+                };
+
+                (
+                    opcode,
+                    wide,
+                    self.reg_or_memory(destination, wide, here)?,
+                    None,
+                )
+            }
+            ("inc" | "dec", [destination]) => {
+                let wide = width(&[destination])?;
+
+                // 16-bit registers have a form of their own
+                let opcode = match (mnemonic, wide && is_register(destination)) {
+                    ("inc", true) => Opcode::Inc(Inc::Reg),
+                    ("inc", false) => Opcode::Inc(Inc::RegOrMem),
+                    (_, true) => Opcode::Dec(Dec::Reg),
+                    (_, false) => Opcode::Dec(Dec::RegOrMem),
+                };
+
+                (
+                    opcode,
+                    wide,
+                    self.reg_or_memory(destination, wide, here)?,
+                    None,
+                )
+            }
+            (
+                "rol" | "ror" | "rcl" | "rcr" | "shl" | "sal" | "shr" | "sar",
+                [destination, count],
+            ) => {
+                let wide = width(&[destination])?;
+                let shift = match mnemonic {
+                    "rol" => Shift::Rol,
+                    "ror" => Shift::Ror,
+                    "rcl" => Shift::Rcl,
+                    "rcr" => Shift::Rcr,
+                    "shl" | "sal" => Shift::Shl,
+                    "shr" => Shift::Shr,
+                    _ => Shift::Sar,
+                };
+
+                let count = match count {
+                    Arg::Register(Register::CL) => Operand::Register(Register::CL),
+                    Arg::Immediate { value, .. } if self.value(value, here)? == 1 => {
+                        Operand::Immediate(1)
+                    }
+                    _ => return Err(InvalidOperands),
+                };
+
+                (
+                    Opcode::Shift(shift),
+                    wide,
+                    self.reg_or_memory(destination, wide, here)?,
+                    Some(count),
+                )
+            }
+            ("push" | "pop", [destination]) => {
+                if !width(&[destination]).unwrap_or(true) {
+                    return Err(InvalidOperands);
+                }
+
+                let opcode = match (mnemonic, destination) {
+                    ("push", Arg::Register(r)) if is_segment(r) => Opcode::Push(Push::Seg),
+                    ("push", Arg::Register(_)) => Opcode::Push(Push::Reg),
+                    ("push", _) => Opcode::Push(Push::RegOrMem),
+                    (_, Arg::Register(r)) if is_segment(r) => Opcode::Pop(Pop::Seg),
+                    (_, Arg::Register(_)) => Opcode::Pop(Pop::Reg),
+                    _ => Opcode::Pop(Pop::RegOrMem),
+                };
+
+                (
+                    opcode,
+                    true,
+                    self.reg_or_memory(destination, true, here)?,
+                    None,
+                )
+            }
+            ("call" | "jmp", [target]) => {
+                let call = mnemonic == "call";
+
+                let (opcode, destination) = match target {
+                    Arg::FarPointer(segment, offset) => {
+                        let segment = self.fit(self.value(segment, here)?, true)?;
+                        let offset = self.fit(self.value(offset, here)?, true)?;
+                        let pointer = Operand::FarPointer(segment, offset);
+
+                        if call {
+                            (Opcode::Call(Call::DirectFar), pointer)
+                        } else {
+                            (Opcode::Jmp(Jmp::DirectFar), pointer)
+                        }
+                    }
+                    Arg::Immediate {
+                        qualifier:
+                            qualifier @ (None
+                            | Some(Qualifier::Short | Qualifier::Near | Qualifier::Word)),
+                        value,
+                    } => {
+                        let short = match qualifier {
+                            // there's no short call
+                            Some(Qualifier::Short) if call => return Err(InvalidOperands),
+                            _ if call => false,
+                            Some(Qualifier::Short) => true,
+                            // word is a near jump too, with its 16 bit increment
+                            Some(_) => false,
+                            // NASM goes for a short jmp whenever it reaches
+                            None if self.near_jumps.contains(&index) => false,
+                            None => {
+                                let increment = self.increment(value, here, prefix_length + 2)?;
+                                let reaches = i8::try_from(increment).is_ok();
+                                if !reaches {
+                                    self.near_jumps.insert(index);
+                                }
+                                reaches
+                            }
+                        };
+
+                        if short {
+                            let increment = self.short_increment(value, here, prefix_length + 2)?;
+                            (Opcode::Jmp(Jmp::Short), increment)
+                        } else {
+                            // a near increment wraps around the segment, so it reaches anywhere
+                            // in it
+                            let target = self.fit(self.value(value, here)?, true)?;
+                            let next = here.wrapping_add(prefix_length + 3);
+                            let increment =
+                                Operand::NearInstPtrIncrement(target.wrapping_sub(next) as i16);
+
+                            if call {
+                                (Opcode::Call(Call::Direct), increment)
+                            } else {
+                                (Opcode::Jmp(Jmp::Direct), increment)
+                            }
+                        }
+                    }
+                    Arg::Memory {
+                        qualifier: Some(Qualifier::Far),
+                        ..
+                    } => {
+                        let pointer = self.operand(target, true, here)?;
+                        if call {
+                            (Opcode::Call(Call::IndirectFar), pointer)
+                        } else {
+                            (Opcode::Jmp(Jmp::IndirectFar), pointer)
+                        }
+                    }
+                    Arg::Register(_)
+                    | Arg::Memory {
+                        qualifier: None | Some(Qualifier::Word),
+                        ..
+                    } => {
+                        if !width(&[target]).unwrap_or(true) {
+                            return Err(InvalidOperands);
+                        }
+
+                        let target = self.operand(target, true, here)?;
+                        if call {
+                            (Opcode::Call(Call::Indirect), target)
+                        } else {
+                            (Opcode::Jmp(Jmp::Indirect), target)
+                        }
+                    }
+                    _ => return Err(InvalidOperands),
+                };
+
+                (opcode, true, destination, None)
+            }
+            (
+                _,
+                [Arg::Immediate {
+                    qualifier: None | Some(Qualifier::Short),
+                    value,
+                }],
+            ) if conditional_jump(mnemonic).is_some() => {
+                let j = conditional_jump(mnemonic).expect("a conditional jump");
+                let increment = self.short_increment(value, here, prefix_length + 2)?;
+
+                (Opcode::J(j), false, increment, None)
+            }
+            ("ret" | "retf", _) => {
+                let opcode = match mnemonic {
+                    "ret" => Opcode::Ret(Ret::Near),
+                    _ => Opcode::Ret(Ret::Far),
+                };
+
+                match args {
+                    [] => return self.encode(prefixes, None, opcode, false, None, None),
+                    [Arg::Immediate { value, .. }] => {
+                        (opcode, true, self.immediate(value, true, here)?, None)
+                    }
+                    _ => return Err(InvalidOperands),
+                }
+            }
+            ("int", [Arg::Immediate { value, .. }]) => (
+                Opcode::Int,
+                false,
+                self.immediate(value, false, here)?,
+                None,
+            ),
+            ("aam" | "aad", [Arg::Immediate { value, .. }]) => {
+                let opcode = match mnemonic {
+                    "aam" => Opcode::Aam,
+                    _ => Opcode::Aad,
+                };
+
+                (opcode, false, self.immediate(value, false, here)?, None)
+            }
+            ("in", [accumulator, port]) | ("out", [port, accumulator])
+                if is_accumulator(accumulator) =>
+            {
+                let wide = width(&[accumulator])?;
+                let fixed = match port {
+                    Arg::Immediate { .. } => true,
+                    Arg::Register(Register::DX) => false,
+                    _ => return Err(InvalidOperands),
+                };
+
+                let opcode = match (mnemonic, fixed) {
+                    ("in", true) => Opcode::In(In::FixedPort),
+                    ("in", false) => Opcode::In(In::VariablePort),
+                    (_, true) => Opcode::Out(Out::FixedPort),
+                    (_, false) => Opcode::Out(Out::VariablePort),
+                };
+
+                let accumulator = self.operand(accumulator, wide, here)?;
+                let port = self.operand(port, false, here)?;
+
+                match mnemonic {
+                    "in" => (opcode, wide, accumulator, Some(port)),
+                    _ => (opcode, wide, port, Some(accumulator)),
+                }
+            }
+            ("lea" | "lds" | "les", [destination, source @ Arg::Memory { .. }]) => {
+                if !width(&[destination])? {
+                    return Err(InvalidOperands);
+                }
+
+                let opcode = match mnemonic {
+                    "lea" => Opcode::Lea,
+                    "lds" => Opcode::Lds,
+                    _ => Opcode::Les,
+                };
+
+                (
+                    opcode,
+                    true,
+                    self.operand(destination, true, here)?,
+                    Some(self.operand(source, true, here)?),
+                )
+            }
+            (_, []) => {
+                let (opcode, wide) = match mnemonic {
+                    "pushf" => (Opcode::Pushf, false),
+                    "popf" => (Opcode::Popf, false),
+                    "movsb" => (Opcode::Movs, false),
+                    "movsw" => (Opcode::Movs, true),
+                    "cmpsb" => (Opcode::Cmps, false),
+                    "cmpsw" => (Opcode::Cmps, true),
+                    "scasb" => (Opcode::Scas, false),
+                    "scasw" => (Opcode::Scas, true),
+                    "lodsb" => (Opcode::Lods, false),
+                    "lodsw" => (Opcode::Lods, true),
+                    "stosb" => (Opcode::Stos, false),
+                    "stosw" => (Opcode::Stos, true),
+                    "int3" => (Opcode::Int3, false),
+                    "into" => (Opcode::Into, false),
+                    "iret" => (Opcode::Iret, false),
+                    "xlat" | "xlatb" => (Opcode::Xlat, false),
+                    "lahf" => (Opcode::Lahf, false),
+                    "sahf" => (Opcode::Sahf, false),
+                    "cbw" => (Opcode::Cbw, false),
+                    "cwd" => (Opcode::Cwd, false),
+                    "daa" => (Opcode::Daa, false),
+                    "das" => (Opcode::Das, false),
+                    "aaa" => (Opcode::Aaa, false),
+                    "aas" => (Opcode::Aas, false),
+                    "aam" => (Opcode::Aam, false),
+                    "aad" => (Opcode::Aad, false),
+                    "clc" => (Opcode::Clc, false),
+                    "stc" => (Opcode::Stc, false),
+                    "cmc" => (Opcode::Cmc, false),
+                    "cld" => (Opcode::Cld, false),
+                    "std" => (Opcode::Std, false),
+                    "cli" => (Opcode::Cli, false),
+                    "sti" => (Opcode::Sti, false),
+                    "hlt" => (Opcode::Hlt, false),
+                    "wait" | "fwait" => (Opcode::Wait, false),
+                    "nop" => (Opcode::Nop, false),
+                    _ if is_mnemonic(mnemonic) => return Err(InvalidOperands),
+                    _ => return Err(AsmErrorReason::UnknownMnemonic),
+                };
+
+                return self.encode(prefixes, None, opcode, wide, None, None);
+            }
+            _ if is_mnemonic(mnemonic) => return Err(InvalidOperands),
+            _ => return Err(AsmErrorReason::UnknownMnemonic),
+        };
+
+        let segment_override = args.iter().find_map(|arg| match arg {
+            Arg::Memory { segment, .. } => *segment,
+            _ => None,
+        });
+
+        self.encode(
+            prefixes,
+            segment_override,
+            opcode,
+            wide,
+            Some(destination),
+            source,
+        )
+    }
+
+    /// The operand of an instruction that takes a register or memory, but no immediate.
+    fn reg_or_memory(&self, arg: &Arg, wide: bool, here: u16) -> Result<Operand, AsmErrorReason> {
+        match arg {
+            Arg::Register(_) | Arg::Memory { .. } => self.operand(arg, wide, here),
+            _ => Err(AsmErrorReason::InvalidOperands),
+        }
+    }
+
+    fn encode(
+        &self,
+        prefixes: &[Prefix],
+        segment_override: Option<Register>,
+        opcode: Opcode,
+        wide: bool,
+        destination: Option<Operand>,
+        source: Option<Operand>,
+    ) -> Result<Vec<u8>, AsmErrorReason> {
+        let repeat = prefixes.iter().find_map(|prefix| match prefix {
+            Prefix::Repeat(repeat) => Some(*repeat),
+            _ => None,
+        });
+
+        // an override in the brackets wins over one in front of the instruction
+        let segment_override = segment_override.or_else(|| {
+            prefixes.iter().find_map(|prefix| match prefix {
+                Prefix::Segment(segment) => Some(*segment),
+                _ => None,
+            })
+        });
+
+        let i = Instruction {
+            opcode,
+            wide,
+            segment_override,
+            repeat,
+            lock: prefixes.iter().any(|prefix| matches!(prefix, Prefix::Lock)),
+            reg_is_destination: None,
+            sign_extend: None,
+            source,
+            destination,
+        };
+
+        // the forms were picked to fit the operands, anything left over is a mismatch like
+        // mov al, bx or a segment register where only general ones go
+        encode(&i).map_err(|_| AsmErrorReason::InvalidOperands)
+    }
+}
+
+/// Whether the operands are words, from whichever of them has a size. An immediate only gets
+/// a say when nothing else does, like in `mov [bx], byte 7`.
+fn width(args: &[&Arg]) -> Result<bool, AsmErrorReason> {
+    let mut wide = None;
+
+    for arg in args {
+        let size = match arg {
+            Arg::Register(r) => Some(is_wide(r)),
+            Arg::Memory { qualifier, .. } => qualifier.and_then(Qualifier::wide),
+            _ => None,
+        };
+
+        match (wide, size) {
+            (Some(a), Some(b)) if a != b => return Err(AsmErrorReason::InvalidOperands),
+            (None, Some(_)) => wide = size,
+            _ => {}
+        }
+    }
+
+    if wide.is_none() {
+        wide = args.iter().find_map(|arg| match arg {
+            Arg::Immediate { qualifier, .. } => qualifier.and_then(Qualifier::wide),
+            _ => None,
+        });
+    }
+
+    wide.ok_or(AsmErrorReason::SizeNotSpecified)
+}
+
+fn is_wide(r: &Register) -> bool {
+    use Register as R;
+    !matches!(
+        r,
+        R::AL | R::BL | R::CL | R::DL | R::AH | R::BH | R::CH | R::DH
+    )
+}
+
+fn is_segment(r: &Register) -> bool {
+    matches!(r, Register::ES | Register::CS | Register::SS | Register::DS)
+}
+
+fn is_register(arg: &Arg) -> bool {
+    matches!(arg, Arg::Register(r) if !is_segment(r))
+}
+
+fn is_accumulator(arg: &Arg) -> bool {
+    matches!(arg, Arg::Register(Register::AL | Register::AX))
+}
+
+fn is_direct_address(arg: &Arg) -> bool {
+    matches!(arg, Arg::Memory { registers, .. } if registers.is_empty())
+}
+
+fn conditional_jump(mnemonic: &str) -> Option<J> {
+    let j = match mnemonic {
+        "jo" => J::Jo,
+        "jno" => J::Jno,
+        "jb" | "jnae" | "jc" => J::Jb,
+        "jnb" | "jae" | "jnc" => J::Jnb,
+        "je" | "jz" => J::Je,
+        "jne" | "jnz" => J::Jne,
+        "jbe" | "jna" => J::Jbe,
+        "ja" | "jnbe" => J::Ja,
+        "js" => J::Js,
+        "jns" => J::Jns,
+        "jp" | "jpe" => J::Jp,
+        "jnp" | "jpo" => J::Jnp,
+        "jl" | "jnge" => J::Jl,
+        "jnl" | "jge" => J::Jnl,
+        "jle" | "jng" => J::Jle,
+        "jg" | "jnle" => J::Jg,
+        "loopnz" | "loopne" => J::Loopnz,
+        "loopz" | "loope" => J::Loopz,
+        "loop" => J::Loop,
+        "jcxz" => J::Jcxz,
+        _ => return None,
+    };
+
+    Some(j)
+}
+
+/// Mnemonics that need operands, to tell wrong operands apart from a typo.
+fn is_mnemonic(mnemonic: &str) -> bool {
+    conditional_jump(mnemonic).is_some()
+        || matches!(
+            mnemonic,
+            "mov"
+                | "add"
+                | "or"
+                | "adc"
+                | "sbb"
+                | "and"
+                | "sub"
+                | "xor"
+                | "cmp"
+                | "test"
+                | "xchg"
+                | "not"
+                | "neg"
+                | "mul"
+                | "imul"
+                | "div"
+                | "idiv"
+                | "inc"
+                | "dec"
+                | "rol"
+                | "ror"
+                | "rcl"
+                | "rcr"
+                | "shl"
+                | "sal"
+                | "shr"
+                | "sar"
+                | "push"
+                | "pop"
+                | "call"
+                | "jmp"
+                | "ret"
+                | "retf"
+                | "int"
+                | "in"
+                | "out"
+                | "lea"
+                | "lds"
+                | "les"
+        )
+}
+
+fn register(name: &str) -> Option<Register> {
+    use Register as R;
+
+    let r = match name {
+        "al" => R::AL,
+        "bl" => R::BL,
+        "cl" => R::CL,
+        "dl" => R::DL,
+        "ah" => R::AH,
+        "bh" => R::BH,
+        "ch" => R::CH,
+        "dh" => R::DH,
+        "ax" => R::AX,
+        "bx" => R::BX,
+        "cx" => R::CX,
+        "dx" => R::DX,
+        "si" => R::SI,
+        "di" => R::DI,
+        "sp" => R::SP,
+        "bp" => R::BP,
+        "es" => R::ES,
+        "cs" => R::CS,
+        "ss" => R::SS,
+        "ds" => R::DS,
+        _ => return None,
+    };
+
+    Some(r)
+}
+
+/// One line of source.
+struct Statement {
+    line: usize,
+    source: String,
+    label: Option<String>,
+    kind: Kind,
+}
+
+enum Kind {
+    /// Nothing to assemble, just a label, a comment or `bits 16`.
+    Empty,
+    /// db or dw.
+    Data { wide: bool, values: Vec<Datum> },
+    /// Prefixes on a line of their own, for the instruction on the next line.
+    Prefixes(Vec<Prefix>),
+    Instruction {
+        prefixes: Vec<Prefix>,
+        /// Lowercase, like registers and keywords. Only labels are case sensitive.
+        mnemonic: String,
+        args: Vec<Arg>,
+    },
+}
+
+enum Prefix {
+    Repeat(Repeat),
+    Lock,
+    /// A segment register in front of the instruction, like `es movsb`, the only way to put an
+    /// override on the instructions without a memory operand.
+    Segment(Register),
+}
+
+impl Prefix {
+    fn byte(&self) -> u8 {
+        match self {
+            Prefix::Repeat(Repeat::Rep) => 0b11110011,
+            Prefix::Repeat(Repeat::Repne) => 0b11110010,
+            Prefix::Lock => 0b11110000,
+            Prefix::Segment(Register::ES) => 0b00100110,
+            Prefix::Segment(Register::CS) => 0b00101110,
+            Prefix::Segment(Register::SS) => 0b00110110,
+            Prefix::Segment(_) => 0b00111110,
+        }
+    }
+}
+
+/// One of the values after db or dw.
+enum Datum {
+    Expr(Expr),
+    /// A string on its own, which gives a byte for every character. dw pads it to whole words.
+    String(Vec<u8>),
+}
+
+enum Arg {
+    Register(Register),
+    Memory {
+        qualifier: Option<Qualifier>,
+        segment: Option<Register>,
+        registers: Vec<Register>,
+        displacement: Option<Expr>,
+    },
+    Immediate {
+        qualifier: Option<Qualifier>,
+        value: Expr,
+    },
+    /// segment:offset
+    FarPointer(Expr, Expr),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Qualifier {
+    Byte,
+    Word,
+    Short,
+    Near,
+    Far,
+}
+
+impl Qualifier {
+    fn wide(self) -> Option<bool> {
+        match self {
+            Qualifier::Byte => Some(false),
+            Qualifier::Word => Some(true),
+            _ => None,
+        }
+    }
+}
+
+enum Expr {
+    Number(i64),
+    Label(String),
+    /// `$`, the address of the start of the line.
+    Here,
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Number(i64),
+    /// Characters in single or double quotes.
+    String(Vec<u8>),
+    Punct(char),
+}
+
+impl Statement {
+    fn parse(line: usize, text: &str) -> Result<Self, AsmError> {
+        // a comment starts at the first semicolon that isn't quoted
+        let mut quote = None;
+        let end = text
+            .char_indices()
+            .find(|(_, c)| {
+                match (quote, *c) {
+                    (None, '\'' | '"') => quote = Some(*c),
+                    (Some(q), c) if c == q => quote = None,
+                    _ => {}
+                }
+                quote.is_none() && *c == ';'
+            })
+            .map_or(text.len(), |(i, _)| i);
+        let source = text[..end].trim().to_string();
+
+        let mut statement = Statement {
+            line,
+            source,
+            label: None,
+            kind: Kind::Empty,
+        };
+
+        let tokens = tokenize(&statement.source).map_err(|reason| statement.error(reason))?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+
+        match parser.statement() {
+            Ok((label, kind)) => {
+                statement.label = label;
+                statement.kind = kind;
+                Ok(statement)
+            }
+            Err(reason) => Err(statement.error(reason)),
+        }
+    }
+
+    fn error(&self, reason: AsmErrorReason) -> AsmError {
+        AsmError {
+            line: self.line,
+            source: self.source.clone(),
+            reason,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, AsmErrorReason> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        if "[]:,+-*()$".contains(c) {
+            tokens.push(Token::Punct(c));
+            continue;
+        }
+
+        if c == '\'' || c == '"' {
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, end)) if end == c => break,
+                    Some((_, c)) => string.push(c),
+                    None => return Err(AsmErrorReason::Syntax),
+                }
+            }
+
+            tokens.push(Token::String(string.into_bytes()));
+            continue;
+        }
+
+        if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err(AsmErrorReason::Syntax);
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some((i, c)) =
+            chars.next_if(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '.')
+        {
+            end = i + c.len_utf8();
+        }
+
+        let word = &text[start..end];
+        if c.is_ascii_digit() {
+            tokens.push(Token::Number(number(word)?));
+        } else {
+            tokens.push(Token::Identifier(word.to_string()));
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Decimal, hex as `0x1f` or `1fh`, or binary as `0b101`.
+fn number(word: &str) -> Result<i64, AsmErrorReason> {
+    let word = word.to_lowercase();
+
+    let parsed = if let Some(hex) = word.strip_suffix('h') {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(hex) = word.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16)
+    } else if let Some(binary) = word.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2)
+    } else {
+        word.parse()
+    };
+
+    parsed.map_err(|_| AsmErrorReason::Syntax)
+}
+
+struct Parser<'t> {
+    tokens: &'t [Token],
+    position: usize,
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'t Token> {
+        let token = self.peek();
+        self.position += 1;
+        token
+    }
+
+    /// Takes the next token if it's `c`.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.position += 1;
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), AsmErrorReason> {
+        match self.eat(c) {
+            true => Ok(()),
+            false => Err(AsmErrorReason::Syntax),
+        }
+    }
+
+    /// The next token as a lowercase keyword, if it's an identifier.
+    fn peek_keyword(&self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Identifier(name)) => Some(name.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    fn statement(&mut self) -> Result<(Option<String>, Kind), AsmErrorReason> {
+        let mut label = None;
+        if let (Some(Token::Identifier(name)), Some(Token::Punct(':'))) =
+            (self.peek(), self.tokens.get(self.position + 1))
+        {
+            label = Some(name.clone());
+            self.position += 2;
+        }
+
+        let Some(mut mnemonic) = self.peek_keyword() else {
+            return match self.peek() {
+                None => Ok((label, Kind::Empty)),
+                Some(_) => Err(AsmErrorReason::Syntax),
+            };
+        };
+        self.position += 1;
+
+        let mut prefixes = Vec::new();
+        loop {
+            let prefix = match mnemonic.as_str() {
+                "rep" | "repe" | "repz" => Prefix::Repeat(Repeat::Rep),
+                "repne" | "repnz" => Prefix::Repeat(Repeat::Repne),
+                "lock" => Prefix::Lock,
+                "es" => Prefix::Segment(Register::ES),
+                "cs" => Prefix::Segment(Register::CS),
+                "ss" => Prefix::Segment(Register::SS),
+                "ds" => Prefix::Segment(Register::DS),
+                _ => break,
+            };
+            prefixes.push(prefix);
+
+            mnemonic = match (self.peek_keyword(), self.peek()) {
+                (Some(mnemonic), _) => mnemonic,
+                (None, None) => return Ok((label, Kind::Prefixes(prefixes))),
+                (None, Some(_)) => return Err(AsmErrorReason::Syntax),
+            };
+            self.position += 1;
+        }
+
+        let kind = match mnemonic.as_str() {
+            "bits" if prefixes.is_empty() => {
+                match self.next() {
+                    Some(Token::Number(16)) => {}
+                    Some(Token::Number(_)) => return Err(AsmErrorReason::InvalidOperands),
+                    _ => return Err(AsmErrorReason::Syntax),
+                }
+                Kind::Empty
+            }
+            "db" | "dw" if prefixes.is_empty() => {
+                let mut values = vec![self.datum()?];
+                while self.eat(',') {
+                    values.push(self.datum()?);
+                }
+
+                Kind::Data {
+                    wide: mnemonic == "dw",
+                    values,
+                }
+            }
+            _ => {
+                let mut args = Vec::new();
+                if self.peek().is_some() {
+                    args.push(self.arg()?);
+                    while self.eat(',') {
+                        args.push(self.arg()?);
+                    }
+                }
+
+                Kind::Instruction {
+                    prefixes,
+                    mnemonic,
+                    args,
+                }
+            }
+        };
+
+        match self.peek() {
+            None => Ok((label, kind)),
+            Some(_) => Err(AsmErrorReason::Syntax),
+        }
+    }
+
+    fn datum(&mut self) -> Result<Datum, AsmErrorReason> {
+        if let Some(Token::String(bytes)) = self.peek() {
+            if matches!(
+                self.tokens.get(self.position + 1),
+                None | Some(Token::Punct(','))
+            ) {
+                self.position += 1;
+                return Ok(Datum::String(bytes.clone()));
+            }
+        }
+
+        Ok(Datum::Expr(self.expression()?))
+    }
+
+    fn arg(&mut self) -> Result<Arg, AsmErrorReason> {
+        let qualifier = match self.peek_keyword().as_deref() {
+            Some("byte") => Some(Qualifier::Byte),
+            Some("word") => Some(Qualifier::Word),
+            Some("short") => Some(Qualifier::Short),
+            Some("near") => Some(Qualifier::Near),
+            Some("far") => Some(Qualifier::Far),
+            _ => None,
+        };
+        if qualifier.is_some() {
+            self.position += 1;
+        }
+
+        if self.eat('[') {
+            return self.memory(qualifier);
+        }
+
+        if let Some(r) = self.peek_keyword().as_deref().and_then(register) {
+            if qualifier.is_some() {
+                return Err(AsmErrorReason::Syntax);
+            }
+
+            self.position += 1;
+            return Ok(Arg::Register(r));
+        }
+
+        let value = self.expression()?;
+        if self.eat(':') {
+            if !matches!(qualifier, None | Some(Qualifier::Far)) {
+                return Err(AsmErrorReason::Syntax);
+            }
+
+            return Ok(Arg::FarPointer(value, self.expression()?));
+        }
+
+        Ok(Arg::Immediate { qualifier, value })
+    }
+
+    /// What's between the brackets, the opening one already taken.
+    fn memory(&mut self, qualifier: Option<Qualifier>) -> Result<Arg, AsmErrorReason> {
+        let mut segment = None;
+        if let Some(r) = self.peek_keyword().as_deref().and_then(register) {
+            if self.tokens.get(self.position + 1) == Some(&Token::Punct(':')) {
+                if !is_segment(&r) {
+                    return Err(AsmErrorReason::InvalidOperands);
+                }
+
+                segment = Some(r);
+                self.position += 2;
+            }
+        }
+
+        let mut registers = Vec::new();
+        let mut displacement: Option<Expr> = None;
+
+        // registers can only be added, everything else makes up the displacement
+        let mut negative = self.eat('-');
+        loop {
+            match self.peek_keyword().as_deref().and_then(register) {
+                Some(r) if !negative => {
+                    self.position += 1;
+                    registers.push(r);
+                }
+                Some(_) => return Err(AsmErrorReason::InvalidOperands),
+                None => {
+                    let mut term = self.term()?;
+                    if negative {
+                        term = Expr::Negate(Box::new(term));
+                    }
+
+                    displacement = Some(match displacement {
+                        Some(d) => Expr::Binary(Box::new(d), '+', Box::new(term)),
+                        None => term,
+                    });
+                }
+            }
+
+            if self.eat(']') {
+                break;
+            }
+
+            negative = match self.next() {
+                Some(Token::Punct('+')) => false,
+                Some(Token::Punct('-')) => true,
+                _ => return Err(AsmErrorReason::Syntax),
+            };
+        }
+
+        Ok(Arg::Memory {
+            qualifier,
+            segment,
+            registers,
+            displacement,
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expr, AsmErrorReason> {
+        let mut expr = self.term()?;
+
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct(op @ ('+' | '-'))) => *op,
+                _ => return Ok(expr),
+            };
+            self.position += 1;
+
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, AsmErrorReason> {
+        let mut expr = self.unary()?;
+
+        while self.eat('*') {
+            expr = Expr::Binary(Box::new(expr), '*', Box::new(self.unary()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, AsmErrorReason> {
+        if self.eat('-') {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+
+        if self.eat('+') {
+            return self.unary();
+        }
+
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(*n)),
+            // a character constant, the first character in the lowest byte
+            Some(Token::String(bytes)) if bytes.len() <= 8 => Ok(Expr::Number(
+                bytes.iter().rev().fold(0, |n, byte| n << 8 | *byte as i64),
+            )),
+            Some(Token::Identifier(name)) => Ok(Expr::Label(name.clone())),
+            Some(Token::Punct('$')) => Ok(Expr::Here),
+            Some(Token::Punct('(')) => {
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            _ => Err(AsmErrorReason::Syntax),
+        }
+    }
+}
@@ -1,12 +1,76 @@
+use std::fmt::Display;
+
 use crate::{
-    add::Add, cmp::Cmp, mov::Mov, sub::Sub, EffectiveAddressCalc, Instruction, Opcode, Operand,
-    Register, Word,
+    adc::Adc, add::Add, and::And, call::Call, cmp::Cmp, dec::Dec, inc::Inc, input::In, jmp::Jmp,
+    mov::Mov, or::Or, output::Out, pop::Pop, push::Push, sbb::Sbb, sub::Sub, test::Test,
+    xchg::Xchg, xor::Xor, EffectiveAddressCalc, Instruction, Opcode, Operand, Register, Repeat,
+    Word,
 };
 
+/// Reads instructions a byte at a time, fetching only as many bytes as each encoding needs.
 pub struct Decoder<'source> {
     input: &'source [u8],
+    /// The next byte to read.
     offset: usize,
-    pub(crate) read_offset: usize,
+    /// Where the instruction being decoded starts, prefixes included.
+    start: usize,
+    /// The opcode byte of the instruction being decoded, after any prefixes.
+    opcode: u8,
+}
+
+/// Why the bytes at some offset couldn't be decoded into an instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeErrorReason {
+    UnknownOpcode,
+    /// The input ended in the middle of an instruction.
+    Truncated,
+    /// The mod/reg/r-m byte names an operand the instruction can't take, like a register
+    /// for lea.
+    InvalidModRm,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    /// Offset into the input of the first byte of the instruction, prefixes included.
+    pub offset: usize,
+    /// The bytes that were read before decoding gave up.
+    pub bytes: Vec<u8>,
+    pub reason: DecodeErrorReason,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            DecodeErrorReason::UnknownOpcode => "unknown opcode",
+            DecodeErrorReason::Truncated => "truncated instruction",
+            DecodeErrorReason::InvalidModRm => "invalid mod/rm in",
+        };
+
+        write!(f, "{}", reason)?;
+        for byte in &self.bytes {
+            write!(f, " {:#04X}", byte)?;
+        }
+        write!(f, " at offset {:#X}", self.offset)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// An instruction along with where in the input it was found and the bytes it was decoded
+/// from, for anything that needs to know about the machine code as well.
+pub struct DecodedInstruction<'source> {
+    pub instruction: Instruction,
+    /// Offset into the input of the first byte, prefixes included.
+    pub offset: usize,
+    /// Every byte of the instruction, prefixes included.
+    pub bytes: &'source [u8],
+}
+
+impl DecodedInstruction<'_> {
+    /// How many bytes the instruction takes up.
+    pub fn encoded_len(&self) -> usize {
+        self.bytes.len()
+    }
 }
 
 impl<'source> Decoder<'source> {
@@ -14,361 +78,902 @@ impl<'source> Decoder<'source> {
         Self {
             input,
             offset: 0,
-            read_offset: 0,
+            start: 0,
+            opcode: 0,
         }
     }
 
-    fn curr_word(&self) -> Option<Word> {
-        debug_assert!(self.offset < self.read_offset);
-        let Some([a, b]) = self.input.get(self.offset..self.offset + 2) else {
-            return None;
-        };
-        Some(Word { high: *a, low: *b })
+    fn peek_byte(&self) -> Option<u8> {
+        self.input.get(self.offset).copied()
+    }
+
+    /// Reads the next byte of the instruction, which has to be there for it to be complete.
+    fn next_byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = self
+            .peek_byte()
+            .ok_or_else(|| self.error(DecodeErrorReason::Truncated))?;
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    /// Reads a little endian word, like 16 bit data or a displacement.
+    fn next_word(&mut self) -> Result<u16, DecodeError> {
+        let low = self.next_byte()?;
+        let high = self.next_byte()?;
+        Ok(Word::new(high, low).into())
     }
 
-    fn next_byte(&mut self) -> Option<u8> {
-        let Some(a) = self.input.get(self.read_offset) else {
+    fn register(&self, code: &u8, wide: &u8) -> Result<Register, DecodeError> {
+        Register::try_from(code, wide).ok_or_else(|| self.error(DecodeErrorReason::InvalidModRm))
+    }
+
+    fn segment_register(&self, code: &u8) -> Result<Register, DecodeError> {
+        Register::try_from_segment(code).ok_or_else(|| self.error(DecodeErrorReason::InvalidModRm))
+    }
+
+    /// Everything read since the start of the current instruction goes into the error.
+    fn error(&self, reason: DecodeErrorReason) -> DecodeError {
+        DecodeError {
+            offset: self.start,
+            bytes: self.input[self.start..self.offset].to_vec(),
+            reason,
+        }
+    }
+
+    /// Segment override prefixes are a byte of their own, in the form 001 SR 110.
+    fn segment_override_prefix(byte: u8) -> Option<Register> {
+        if byte & 0b11100111 != 0b00100110 {
             return None;
-        };
-        self.offset = self.read_offset;
-        self.read_offset += 1;
-        Some(*a)
-    }
-
-    fn next_word(&mut self) -> Option<Word> {
-        self.offset = self.read_offset;
-        self.read_offset += 2;
-
-        self.curr_word()
-    }
-
-    pub fn decode_next(&mut self) -> Option<Instruction> {
-        if let Some(word) = self.next_word() {
-            let opcode = Opcode::try_from(&word).unwrap();
-            let i = match &opcode {
-                Opcode::Mov(m) => match m {
-                    Mov::ImmToReg => self.decode_mov_immediate_to_register(opcode),
-                    Mov::RM => self.decode_register_memory_to_from_either(opcode),
-                    Mov::ImmToRegOrMem => self.decode_mov_immediate_to_reg_or_memory(opcode),
-                    Mov::MemToAcc => self.decode_mov_mem_to_acc(opcode),
-                    Mov::AccToMem => self.decode_mov_acc_to_mem(opcode),
-                },
-                Opcode::Add(a) => match a {
-                    Add::RM => self.decode_register_memory_to_from_either(opcode),
-                    Add::ImmToRegOrMem => {
-                        self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
-                    }
-                    Add::ImmToAcc => self.decode_immediate_to_acc(opcode),
-                },
-                Opcode::Sub(s) => match s {
-                    Sub::RM => self.decode_register_memory_to_from_either(opcode),
-                    Sub::ImmToRegOrMem => {
-                        self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
-                    }
-                    Sub::ImmToAcc => self.decode_immediate_to_acc(opcode),
-                },
-                Opcode::Cmp(c) => match c {
-                    Cmp::RM => self.decode_register_memory_to_from_either(opcode),
-                    Cmp::ImmToRegOrMem => {
-                        self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
-                    }
-                    Cmp::ImmToAcc => self.decode_immediate_to_acc(opcode),
-                },
-                Opcode::J(_) => self.decode_jump(opcode),
-            };
-
-            return Some(i);
         }
 
-        None
+        Register::try_from_segment(&((byte >> 3) & 0b11))
     }
 
-    fn decode_register_memory_to_from_either(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
+    fn repeat_prefix(byte: u8) -> Option<Repeat> {
+        match byte {
+            0b11110011 => Some(Repeat::Rep),
+            0b11110010 => Some(Repeat::Repne),
+            _ => None,
+        }
+    }
+
+    /// Opcodes that share their byte between several instructions, told apart by the REG field
+    /// of the mod reg r/m byte.
+    fn is_group_opcode(byte: u8) -> bool {
+        matches!(
+            byte,
+            0b10000000..=0b10000011 | 0b11010000..=0b11010011 | 0b11110110 | 0b11110111 | 0b11111110 | 0b11111111
+        )
+    }
+
+    /// After a [`DecodeError`], steps over the first byte of the instruction that couldn't be
+    /// decoded and returns it, so decoding carries on from the byte after.
+    pub fn skip_undecodable(&mut self) -> u8 {
+        self.offset = self.start + 1;
+        self.input[self.start]
+    }
+
+    /// Decodes the instruction at the current offset, or returns `Ok(None)` at the end of
+    /// the input.
+    pub fn decode_next(&mut self) -> Result<Option<Instruction>, DecodeError> {
+        self.start = self.offset;
+
+        if self.peek_byte().is_none() {
+            return Ok(None);
+        }
+
+        let mut segment_override = None;
+        let mut repeat = None;
+        let mut lock = false;
+
+        // prefixes can come in any order, the last one of a kind wins
+        loop {
+            let byte = self.next_byte()?;
+
+            if let Some(segment) = Self::segment_override_prefix(byte) {
+                segment_override = Some(segment);
+            } else if let Some(r) = Self::repeat_prefix(byte) {
+                repeat = Some(r);
+            } else if byte == 0b11110000 {
+                lock = true;
+            } else {
+                self.opcode = byte;
+                break;
+            }
+        }
+
+        // some opcodes keep part of their encoding in the REG field of the byte after, which
+        // is only peeked at here and read again as the mod reg r/m byte. If it's missing, the
+        // read reports the instruction as truncated.
+        let word = Word::new(self.opcode, self.peek_byte().unwrap_or(0));
+
+        let Some(opcode) = Opcode::try_from(&word) else {
+            // the opcode is known but the REG field doesn't pick any of its instructions, so
+            // it's the mod reg r/m byte that's at fault
+            if Self::is_group_opcode(self.opcode) {
+                self.next_byte()?;
+                return Err(self.error(DecodeErrorReason::InvalidModRm));
+            }
+
+            return Err(self.error(DecodeErrorReason::UnknownOpcode));
+        };
+
+        let mut i = match &opcode {
+            Opcode::Mov(m) => match m {
+                Mov::ImmToReg => self.decode_mov_immediate_to_register(opcode),
+                Mov::RM => self.decode_register_memory_to_from_either(opcode),
+                Mov::ImmToRegOrMem => self.decode_immediate_to_reg_or_memory(opcode),
+                Mov::MemToAcc => self.decode_mov_mem_to_acc(opcode),
+                Mov::AccToMem => self.decode_mov_acc_to_mem(opcode),
+                Mov::RegOrMemToSeg | Mov::SegToRegOrMem => self.decode_mov_segment_register(opcode),
+            },
+            Opcode::Add(a) => match a {
+                Add::RM => self.decode_register_memory_to_from_either(opcode),
+                Add::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Add::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Adc(a) => match a {
+                Adc::RM => self.decode_register_memory_to_from_either(opcode),
+                Adc::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Adc::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Sub(s) => match s {
+                Sub::RM => self.decode_register_memory_to_from_either(opcode),
+                Sub::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Sub::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Sbb(s) => match s {
+                Sbb::RM => self.decode_register_memory_to_from_either(opcode),
+                Sbb::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Sbb::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Cmp(c) => match c {
+                Cmp::RM => self.decode_register_memory_to_from_either(opcode),
+                Cmp::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Cmp::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::And(a) => match a {
+                And::RM => self.decode_register_memory_to_from_either(opcode),
+                And::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                And::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Or(o) => match o {
+                Or::RM => self.decode_register_memory_to_from_either(opcode),
+                Or::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Or::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Xor(x) => match x {
+                Xor::RM => self.decode_register_memory_to_from_either(opcode),
+                Xor::ImmToRegOrMem => {
+                    self.decode_immediate_to_reg_or_memory_with_sign_extension(opcode)
+                }
+                Xor::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Test(t) => match t {
+                Test::RM => self.decode_register_memory_to_from_either(opcode),
+                Test::ImmToRegOrMem => self.decode_immediate_to_reg_or_memory(opcode),
+                Test::ImmToAcc => self.decode_immediate_to_acc(opcode),
+            },
+            Opcode::Not | Opcode::Neg | Opcode::Mul | Opcode::Imul | Opcode::Div | Opcode::Idiv => {
+                self.decode_reg_or_memory(opcode)
+            }
+            Opcode::Inc(i) => match i {
+                Inc::RegOrMem => self.decode_reg_or_memory(opcode),
+                Inc::Reg => self.decode_register(opcode),
+            },
+            Opcode::Dec(d) => match d {
+                Dec::RegOrMem => self.decode_reg_or_memory(opcode),
+                Dec::Reg => self.decode_register(opcode),
+            },
+            Opcode::Shift(_) => self.decode_shift(opcode),
+            Opcode::Call(c) => match c {
+                Call::Direct => self.decode_near_jump(opcode),
+                Call::DirectFar => self.decode_far_pointer(opcode),
+                Call::Indirect | Call::IndirectFar => self.decode_reg_or_memory(opcode),
+            },
+            Opcode::Jmp(j) => match j {
+                Jmp::Short => self.decode_jump(opcode),
+                Jmp::Direct => self.decode_near_jump(opcode),
+                Jmp::DirectFar => self.decode_far_pointer(opcode),
+                Jmp::Indirect | Jmp::IndirectFar => self.decode_reg_or_memory(opcode),
+            },
+            Opcode::Ret(_) => self.decode_ret(opcode),
+            Opcode::Int => self.decode_interrupt(opcode),
+            Opcode::Xchg(x) => match x {
+                Xchg::RM => self.decode_register_memory_to_from_either(opcode),
+                Xchg::RegWithAcc => self.decode_xchg_with_acc(opcode),
+            },
+            Opcode::Lea | Opcode::Lds | Opcode::Les => self.decode_load_address(opcode),
+            Opcode::Int3
+            | Opcode::Into
+            | Opcode::Iret
+            | Opcode::Xlat
+            | Opcode::Lahf
+            | Opcode::Sahf
+            | Opcode::Cbw
+            | Opcode::Cwd
+            | Opcode::Daa
+            | Opcode::Das
+            | Opcode::Aaa
+            | Opcode::Aas
+            | Opcode::Clc
+            | Opcode::Stc
+            | Opcode::Cmc
+            | Opcode::Cld
+            | Opcode::Std
+            | Opcode::Cli
+            | Opcode::Sti
+            | Opcode::Hlt
+            | Opcode::Wait
+            | Opcode::Nop => self.decode_no_operands(opcode),
+            Opcode::Esc => self.decode_escape(opcode),
+            Opcode::In(_) | Opcode::Out(_) => self.decode_port_transfer(opcode),
+            Opcode::Aam | Opcode::Aad => self.decode_ascii_adjust_base(opcode),
+            Opcode::Movs | Opcode::Cmps | Opcode::Scas | Opcode::Lods | Opcode::Stos => {
+                self.decode_string(opcode)
+            }
+            Opcode::Push(p) => match p {
+                Push::RegOrMem => self.decode_reg_or_memory(opcode),
+                Push::Reg => self.decode_register(opcode),
+                Push::Seg => self.decode_segment_register(opcode),
+            },
+            Opcode::Pop(p) => match p {
+                Pop::RegOrMem => self.decode_reg_or_memory(opcode),
+                Pop::Reg => self.decode_register(opcode),
+                Pop::Seg => self.decode_segment_register(opcode),
+            },
+            Opcode::Pushf | Opcode::Popf => self.decode_no_operands(opcode),
+            Opcode::J(_) => self.decode_jump(opcode),
+        }?;
+
+        i.segment_override = segment_override;
+        i.repeat = repeat;
+        i.lock = lock;
+
+        Ok(Some(i))
+    }
+
+    fn decode_register_memory_to_from_either(
+        &mut self,
+        opcode: Opcode,
+    ) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
 
         let destination;
         let source;
 
         // D
         let d_mask = 0x02;
-        let reg_is_destination = (d_mask & word.high) == d_mask;
+        let reg_is_destination = (d_mask & self.opcode) == d_mask;
 
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
-
-        // MOD
-        let mode = (word.low & 0b11000000) >> 6;
+        let wide = w_mask & self.opcode;
 
         // REG
-        let reg_code = (word.low & 0b00111000) >> 3;
+        let reg_code = (mod_rm & 0b00111000) >> 3;
+
+        if reg_is_destination {
+            destination = Operand::Register(self.register(&reg_code, &wide)?);
+            source = self.decode_mod_rm_operand(mod_rm, wide)?;
+        } else {
+            source = Operand::Register(self.register(&reg_code, &wide)?);
+            destination = self.decode_mod_rm_operand(mod_rm, wide)?;
+        }
+
+        Ok(Instruction {
+            opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: Some(reg_is_destination),
+            sign_extend: None,
+            source: Some(source),
+            destination: Some(destination),
+        })
+    }
+
+    /// Reads whatever displacement the MOD and R/M fields of `mod_rm` call for.
+    fn decode_mod_rm_operand(&mut self, mod_rm: u8, wide: u8) -> Result<Operand, DecodeError> {
+        // MOD
+        let mode = (mod_rm & 0b11000000) >> 6;
 
         // R/M
-        let rm = word.low & 0x07;
+        let rm = mod_rm & 0x07;
 
-        let mut get_other_operand = || match mode {
+        match mode {
             0b00 => {
-                let eac = EffectiveAddressCalc::with_no_disp(rm, || {
-                    self.next_word().unwrap().little_endian().into()
-                });
-                Operand::MemoryAddress(eac)
+                let eac = EffectiveAddressCalc::with_no_disp(rm, || self.next_word())?;
+                Ok(Operand::MemoryAddress(eac))
             }
             0b01 => {
                 let eac = EffectiveAddressCalc::with_disp(
                     rm,
-                    self.next_byte().unwrap() /* should sign extends so...*/ as i8 as i16,
+                    self.next_byte()? /* should sign extends so...*/ as i8 as i16,
                 );
-                Operand::MemoryAddress(eac)
+                Ok(Operand::MemoryAddress(eac))
             }
             0b10 => {
-                let displacement = self.next_word().unwrap().little_endian();
-                let eac = EffectiveAddressCalc::with_disp(rm, displacement.into());
-                Operand::MemoryAddress(eac)
-            }
-            0b11 => {
-                let rm_reg_code = word.low & 0b00000111;
-                Operand::Register(Register::try_from(&rm_reg_code, &wide).unwrap())
+                let displacement = self.next_word()?;
+                let eac = EffectiveAddressCalc::with_disp(rm, displacement as i16);
+                Ok(Operand::MemoryAddress(eac))
             }
+            0b11 => Ok(Operand::Register(self.register(&rm, &wide)?)),
             _ => unreachable!(),
-        };
+        }
+    }
 
-        if reg_is_destination {
-            destination = Operand::Register(Register::try_from(&reg_code, &wide).unwrap());
-            source = get_other_operand();
-        } else {
-            source = Operand::Register(Register::try_from(&reg_code, &wide).unwrap());
-            destination = get_other_operand();
+    fn decode_reg_or_memory(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
+
+        // W
+        let w_mask = 1;
+        let wide = w_mask & self.opcode;
+
+        let destination = self.decode_mod_rm_operand(mod_rm, wide)?;
+
+        // a far pointer is two words, so it can only come from memory
+        let far = matches!(
+            opcode,
+            Opcode::Call(Call::IndirectFar) | Opcode::Jmp(Jmp::IndirectFar)
+        );
+        if far && matches!(destination, Operand::Register(_)) {
+            return Err(self.error(DecodeErrorReason::InvalidModRm));
         }
 
-        Instruction {
+        Ok(Instruction {
             opcode,
-            source: Some(source),
-            destination,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(destination),
+        })
+    }
+
+    fn decode_register(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // REG
+        let reg_code = 0b00000111 & self.opcode;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::Register(self.register(&reg_code, &1)?)),
+        })
+    }
+
+    fn decode_xchg_with_acc(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // REG
+        let reg_code = 0b00000111 & self.opcode;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(Operand::Register(self.register(&reg_code, &1)?)),
+            destination: Some(Operand::Register(Register::AX)),
+        })
+    }
+
+    /// lea, lds and les always load a word register from a memory operand, they have no D or W
+    /// bits to look at.
+    fn decode_load_address(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
+
+        // REG
+        let reg_code = (mod_rm & 0b00111000) >> 3;
+
+        let source = self.decode_mod_rm_operand(mod_rm, 1)?;
+
+        // a register has no address to load
+        if matches!(source, Operand::Register(_)) {
+            return Err(self.error(DecodeErrorReason::InvalidModRm));
         }
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(source),
+            destination: Some(Operand::Register(self.register(&reg_code, &1)?)),
+        })
+    }
+
+    fn decode_segment_register(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // SR
+        let sr_code = (self.opcode & 0b00011000) >> 3;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::Register(self.segment_register(&sr_code)?)),
+        })
+    }
+
+    fn decode_no_operands(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: None,
+        })
     }
 
-    fn decode_mov_immediate_to_register(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
+    fn decode_string(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // W
+        let w_mask = 1;
+        let wide = w_mask & self.opcode;
+
+        Ok(Instruction {
+            opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: None,
+        })
+    }
+
+    fn decode_shift(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
+
+        // V
+        let v_mask = 0x02;
+        let count_in_cl = (v_mask & self.opcode) == v_mask;
 
         // W
-        let wide = (0b00001000 & word.high) >> 3;
+        let w_mask = 1;
+        let wide = w_mask & self.opcode;
+
+        let destination = self.decode_mod_rm_operand(mod_rm, wide)?;
+
+        let source = if count_in_cl {
+            Operand::Register(Register::CL)
+        } else {
+            Operand::Immediate(1)
+        };
+
+        Ok(Instruction {
+            opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(source),
+            destination: Some(destination),
+        })
+    }
+
+    fn decode_mov_immediate_to_register(
+        &mut self,
+        opcode: Opcode,
+    ) -> Result<Instruction, DecodeError> {
+        // W
+        let wide = (0b00001000 & self.opcode) >> 3;
 
         // REG
-        let reg_code = 0b00000111 & word.high;
+        let reg_code = 0b00000111 & self.opcode;
 
         let source = if wide == 0 {
-            Operand::Immediate(word.low as u16)
+            Operand::Immediate(self.next_byte()? as u16)
         } else {
-            let next_byte = self.next_byte().expect("a byte after current word");
-            let next_word = Word::new(next_byte, word.low);
-            let value = next_word.into();
-            Operand::Immediate(value)
+            Operand::Immediate(self.next_word()?)
         };
 
-        Instruction {
+        Ok(Instruction {
             opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: Some(source),
-            destination: Operand::Register(Register::try_from(&reg_code, &wide).unwrap()),
-        }
+            destination: Some(Operand::Register(self.register(&reg_code, &wide)?)),
+        })
     }
 
-    fn decode_mov_immediate_to_reg_or_memory(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
+    fn decode_immediate_to_reg_or_memory(
+        &mut self,
+        opcode: Opcode,
+    ) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
 
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
-
-        // MOD
-        let mode = (word.low & 0b11000000) >> 6;
+        let wide = w_mask & self.opcode;
 
-        // R/M
-        let rm = word.low & 0x07;
+        let destination = self.decode_mod_rm_operand(mod_rm, wide)?;
 
-        let mut get_destination_operand = || match mode {
-            0b00 => {
-                let eac = EffectiveAddressCalc::with_no_disp(rm, || {
-                    self.next_word().unwrap().little_endian().into()
-                });
-                Operand::MemoryAddress(eac)
-            }
-            0b01 => {
-                let eac = EffectiveAddressCalc::with_disp(
-                    rm,
-                    self.next_byte().unwrap() /* should sign extends so...*/ as i8 as i16,
-                );
-                Operand::MemoryAddress(eac)
-            }
-            0b10 => {
-                let displacement = self.next_word().unwrap().little_endian();
-                let eac = EffectiveAddressCalc::with_disp(rm, displacement.into());
-                Operand::MemoryAddress(eac)
-            }
-            0b11 => {
-                let rm_reg_code = word.low & 0b00000111;
-                Operand::Register(Register::try_from(&rm_reg_code, &wide).unwrap())
-            }
-            _ => unreachable!(),
+        let source = if wide == 1 {
+            Operand::WordImmediate(self.next_word()?)
+        } else {
+            Operand::ByteImmediate(self.next_byte()?)
         };
 
-        let destination = get_destination_operand();
+        Ok(Instruction {
+            opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(source),
+            destination: Some(destination),
+        })
+    }
 
-        let source = if wide == 1 {
-            let data = self.next_word().unwrap().little_endian();
-            Operand::WordImmediate(data.into())
+    fn decode_mov_segment_register(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
+
+        // D
+        let d_mask = 0x02;
+        let segment_is_destination = (d_mask & self.opcode) == d_mask;
+
+        // SR
+        let sr_code = (mod_rm & 0b00011000) >> 3;
+        let segment = Operand::Register(self.segment_register(&sr_code)?);
+
+        // segment registers are always a word wide
+        let reg_or_memory = self.decode_mod_rm_operand(mod_rm, 1)?;
+
+        let (destination, source) = if segment_is_destination {
+            (segment, reg_or_memory)
         } else {
-            let data = self.next_byte().unwrap();
-            Operand::ByteImmediate(data)
+            (reg_or_memory, segment)
         };
 
-        Instruction {
+        Ok(Instruction {
             opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: Some(source),
-            destination,
-        }
+            destination: Some(destination),
+        })
     }
 
-    fn decode_mov_mem_to_acc(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
-
+    fn decode_mov_mem_to_acc(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
+        let wide = w_mask & self.opcode;
 
-        let addr: u16 = if wide == 1 {
-            Word::new(self.next_byte().unwrap(), word.low).into()
-        } else {
-            word.low as u16
-        };
+        // the address is always 16 bits, W only picks between al and ax
+        let addr = self.next_word()?;
 
-        Instruction {
+        Ok(Instruction {
             opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: Some(Operand::MemoryAddress(EffectiveAddressCalc::DirectAddress(
                 addr,
             ))),
-            destination: Operand::Register(Register::AX),
-        }
+            destination: Some(Operand::Register(self.register(&0, &wide)?)),
+        })
     }
 
-    fn decode_mov_acc_to_mem(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
-
+    fn decode_mov_acc_to_mem(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
+        let wide = w_mask & self.opcode;
 
-        let addr: u16 = if wide == 1 {
-            Word::new(self.next_byte().unwrap(), word.low).into()
-        } else {
-            word.low as u16
-        };
+        // the address is always 16 bits, W only picks between al and ax
+        let addr = self.next_word()?;
 
-        Instruction {
+        Ok(Instruction {
             opcode,
-            source: Some(Operand::Register(Register::AX)),
-            destination: Operand::MemoryAddress(EffectiveAddressCalc::DirectAddress(addr)),
-        }
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(Operand::Register(self.register(&0, &wide)?)),
+            destination: Some(Operand::MemoryAddress(EffectiveAddressCalc::DirectAddress(
+                addr,
+            ))),
+        })
     }
 
-    fn decode_immediate_to_acc(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
-
+    fn decode_immediate_to_acc(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
+        let wide = w_mask & self.opcode;
 
         let (imm, reg) = if wide == 1 {
-            let imm = Word::new(self.next_byte().unwrap(), word.low).into();
-            (imm, Register::AX)
+            (self.next_word()?, Register::AX)
         } else {
-            let imm = word.low as u16;
-            (imm, Register::AL)
+            (self.next_byte()? as u16, Register::AL)
         };
 
-        Instruction {
+        Ok(Instruction {
             opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: Some(Operand::Immediate(imm)),
-            destination: Operand::Register(reg),
-        }
+            destination: Some(Operand::Register(reg)),
+        })
     }
 
     fn decode_immediate_to_reg_or_memory_with_sign_extension(
         &mut self,
         opcode: Opcode,
-    ) -> Instruction {
-        let word = self.curr_word().unwrap();
+    ) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
 
         // S
-        let sign_extend = (word.high & 0b10) >> 1;
+        let sign_extend = (self.opcode & 0b10) >> 1;
 
         // W
         let w_mask = 1;
-        let wide = w_mask & word.high;
-
-        // MOD
-        let mode = (word.low & 0b11000000) >> 6;
-
-        // R/M
-        let rm = word.low & 0x07;
-
-        let mut get_destination_operand = || match mode {
-            0b00 => {
-                let eac = EffectiveAddressCalc::with_no_disp(rm, || {
-                    self.next_word().unwrap().little_endian().into()
-                });
-                Operand::MemoryAddress(eac)
-            }
-            0b01 => {
-                let eac = EffectiveAddressCalc::with_disp(
-                    rm,
-                    self.next_byte().unwrap() /* should sign extends so...*/ as i8 as i16,
-                );
-                Operand::MemoryAddress(eac)
-            }
-            0b10 => {
-                let displacement = self.next_word().unwrap().little_endian();
-                let eac = EffectiveAddressCalc::with_disp(rm, displacement.into());
-                Operand::MemoryAddress(eac)
-            }
-            0b11 => {
-                let rm_reg_code = word.low & 0b00000111;
-                Operand::Register(Register::try_from(&rm_reg_code, &wide).unwrap())
-            }
-            _ => unreachable!(),
-        };
+        let wide = w_mask & self.opcode;
 
-        let destination = get_destination_operand();
+        let destination = self.decode_mod_rm_operand(mod_rm, wide)?;
 
         let source = match (sign_extend, wide) {
-            (0, 1) => {
-                let data = self.next_word().unwrap().little_endian();
-                Operand::WordImmediate(data.into())
-            }
+            (0, 1) => Operand::WordImmediate(self.next_word()?),
             (1, 1) => {
-                let data = self.next_byte().unwrap();
+                let data = self.next_byte()?;
                 Operand::WordImmediate(data as i8 as i16 as u16) // casts are for sign extending
             }
-            _ => {
-                let data = self.next_byte().unwrap();
-                Operand::ByteImmediate(data)
+            _ => Operand::ByteImmediate(self.next_byte()?),
+        };
+
+        Ok(Instruction {
+            opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: Some(sign_extend == 1),
+            source: Some(source),
+            destination: Some(destination),
+        })
+    }
+
+    fn decode_jump(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let inc = self.next_byte()? as i8;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::InstPtrIncrement(inc)),
+        })
+    }
+
+    fn decode_near_jump(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let inc = self.next_word()? as i16;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::NearInstPtrIncrement(inc)),
+        })
+    }
+
+    fn decode_far_pointer(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // the offset comes first, then the segment
+        let offset = self.next_word()?;
+        let segment = self.next_word()?;
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::FarPointer(segment, offset)),
+        })
+    }
+
+    fn decode_interrupt(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let vector = self.next_byte()?;
+
+        Ok(Instruction {
+            opcode,
+            wide: false,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: Some(Operand::Immediate(vector as u16)),
+        })
+    }
+
+    fn decode_port_transfer(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // W
+        let w_mask = 1;
+        let wide = w_mask & self.opcode;
+
+        let acc = Operand::Register(if wide == 1 {
+            Register::AX
+        } else {
+            Register::AL
+        });
+
+        let port = match &opcode {
+            Opcode::In(In::FixedPort) | Opcode::Out(Out::FixedPort) => {
+                Operand::Immediate(self.next_byte()? as u16)
             }
+            _ => Operand::Register(Register::DX),
         };
 
-        Instruction {
+        let (destination, source) = match &opcode {
+            Opcode::In(_) => (acc, port),
+            _ => (port, acc),
+        };
+
+        Ok(Instruction {
             opcode,
+            wide: wide == 1,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: Some(source),
+            destination: Some(destination),
+        })
+    }
+
+    fn decode_ascii_adjust_base(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // the second byte is the number base, which is left out when it's the usual 10
+        let destination = match self.next_byte()? {
+            10 => None,
+            base => Some(Operand::Immediate(base as u16)),
+        };
+
+        Ok(Instruction {
+            opcode,
+            wide: false,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
             destination,
-        }
+        })
     }
 
-    fn decode_jump(&mut self, opcode: Opcode) -> Instruction {
-        let word = self.curr_word().unwrap();
-        let inc = word.low as i8;
+    fn decode_escape(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        let mod_rm = self.next_byte()?;
 
-        Instruction {
+        // the low 3 bits of the opcode and the REG field together make the coprocessor's opcode
+        let external_opcode = ((self.opcode & 0b111) << 3) | ((mod_rm & 0b00111000) >> 3);
+        let source = self.decode_mod_rm_operand(mod_rm, 1)?;
+
+        Ok(Instruction {
             opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: Some(source),
+            destination: Some(Operand::Immediate(external_opcode as u16)),
+        })
+    }
+
+    fn decode_ret(&mut self, opcode: Opcode) -> Result<Instruction, DecodeError> {
+        // the forms with the low bit clear pop an extra imm16 bytes off the stack
+        let destination = if self.opcode & 1 == 0 {
+            Some(Operand::Immediate(self.next_word()?))
+        } else {
+            None
+        };
+
+        Ok(Instruction {
+            opcode,
+            wide: true,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
             source: None,
-            destination: Operand::InstPtrIncrement(inc),
+            destination,
+        })
+    }
+}
+
+/// Goes through the input an instruction at a time. Bytes that don't decode are reported and
+/// then skipped one at a time, like [`Decoder::skip_undecodable`] does, so iteration always
+/// makes it to the end of the input.
+impl<'source> Iterator for Decoder<'source> {
+    type Item = Result<DecodedInstruction<'source>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.decode_next() {
+            Ok(Some(instruction)) => Some(Ok(DecodedInstruction {
+                instruction,
+                offset: self.start,
+                bytes: &self.input[self.start..self.offset],
+            })),
+            Ok(None) => None,
+            Err(err) => {
+                self.skip_undecodable();
+                Some(Err(err))
+            }
         }
     }
 }
@@ -0,0 +1,699 @@
+use std::fmt::Display;
+
+use crate::{
+    adc::Adc, add::Add, and::And, call::Call, cmp::Cmp, dec::Dec, inc::Inc, input::In, jmp::Jmp,
+    jump::J, mov::Mov, or::Or, output::Out, pop::Pop, push::Push, ret::Ret, sbb::Sbb, shift::Shift,
+    sub::Sub, test::Test, xchg::Xchg, xor::Xor, EffectiveAddressCalc, Instruction, Opcode, Operand,
+    Register, Repeat,
+};
+
+/// Why an instruction couldn't be turned into machine code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodeErrorReason {
+    /// The operands don't fit the encoding form the opcode asks for, like a memory operand
+    /// for mov from an immediate to a register.
+    InvalidOperands,
+    /// A label that hasn't been resolved to an offset yet.
+    UnresolvedLabel,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodeError {
+    /// The instruction as it would be printed.
+    pub instruction: String,
+    pub reason: EncodeErrorReason,
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            EncodeErrorReason::InvalidOperands => "invalid operands",
+            EncodeErrorReason::UnresolvedLabel => "unresolved label",
+        };
+
+        write!(f, "can't encode `{}`: {}", self.instruction, reason)
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Turns an instruction back into machine code, the inverse of [`crate::decode::Decoder`].
+///
+/// The opcode already says which encoding form to use. An instruction from the
+/// [`crate::decode::Decoder`] also remembers its D and S bits, so register to register forms
+/// and sign extended immediates come back the way they were read. The choices left are made
+/// the way NASM makes them, for decoded instructions as well:
+///
+/// - the shortest displacement, so `8B 87 05 00` comes back as `8B 47 05`
+/// - S set whenever a word immediate fits in a byte, and never for bytes, so `82 C3 05`
+///   comes back as `80 C3 05`
+/// - D set only when the source is in memory
+/// - one prefix of each kind, in the order lock, rep, segment override
+pub fn encode(i: &Instruction) -> Result<Vec<u8>, EncodeError> {
+    let mut encoder = Encoder {
+        i,
+        bytes: Vec::new(),
+    };
+
+    encoder.encode_prefixes()?;
+    encoder.encode_instruction()?;
+
+    Ok(encoder.bytes)
+}
+
+struct Encoder<'i> {
+    i: &'i Instruction,
+    bytes: Vec<u8>,
+}
+
+impl<'i> Encoder<'i> {
+    fn error(&self, reason: EncodeErrorReason) -> EncodeError {
+        EncodeError {
+            instruction: self.i.to_string(),
+            reason,
+        }
+    }
+
+    fn destination(&self) -> Result<&'i Operand, EncodeError> {
+        self.i
+            .destination
+            .as_ref()
+            .ok_or_else(|| self.error(EncodeErrorReason::InvalidOperands))
+    }
+
+    fn source(&self) -> Result<&'i Operand, EncodeError> {
+        self.i
+            .source
+            .as_ref()
+            .ok_or_else(|| self.error(EncodeErrorReason::InvalidOperands))
+    }
+
+    /// W
+    fn w(&self) -> u8 {
+        self.i.wide as u8
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    /// Words go out low byte first.
+    fn word(&mut self, word: u16) {
+        self.bytes.extend(word.to_le_bytes());
+    }
+
+    /// A byte or a word of immediate data, depending on W.
+    fn data(&mut self, value: u16) {
+        if self.i.wide {
+            self.word(value);
+        } else {
+            self.byte(value as u8);
+        }
+    }
+
+    fn immediate(&self, operand: &Operand) -> Result<u16, EncodeError> {
+        match operand {
+            Operand::Immediate(value) | Operand::WordImmediate(value) => Ok(*value),
+            Operand::ByteImmediate(value) => Ok(*value as u16),
+            Operand::Label(_) => Err(self.error(EncodeErrorReason::UnresolvedLabel)),
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    /// The REG code of a general register, which has to be as wide as W says.
+    fn register(&self, operand: &Operand) -> Result<u8, EncodeError> {
+        use Register as R;
+
+        let Operand::Register(register) = operand else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+
+        match (register, self.i.wide) {
+            (R::AL, false) | (R::AX, true) => Ok(0b000),
+            (R::CL, false) | (R::CX, true) => Ok(0b001),
+            (R::DL, false) | (R::DX, true) => Ok(0b010),
+            (R::BL, false) | (R::BX, true) => Ok(0b011),
+            (R::AH, false) | (R::SP, true) => Ok(0b100),
+            (R::CH, false) | (R::BP, true) => Ok(0b101),
+            (R::DH, false) | (R::SI, true) => Ok(0b110),
+            (R::BH, false) | (R::DI, true) => Ok(0b111),
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    /// The SR code of a segment register.
+    fn segment_register(&self, register: &Register) -> Result<u8, EncodeError> {
+        match register {
+            Register::ES => Ok(0b00),
+            Register::CS => Ok(0b01),
+            Register::SS => Ok(0b10),
+            Register::DS => Ok(0b11),
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    fn accumulator(&self, operand: &Operand) -> Result<(), EncodeError> {
+        match (operand, self.i.wide) {
+            (Operand::Register(Register::AL), false) | (Operand::Register(Register::AX), true) => {
+                Ok(())
+            }
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    /// The mod reg r/m byte and whatever displacement follows it.
+    fn mod_rm(&mut self, reg: u8, operand: &Operand) -> Result<(), EncodeError> {
+        use EffectiveAddressCalc as E;
+        use Register as R;
+
+        let eac = match operand {
+            Operand::Register(_) => {
+                let rm = self.register(operand)?;
+                self.byte(0b11000000 | reg << 3 | rm);
+                return Ok(());
+            }
+            Operand::MemoryAddress(eac) => eac,
+            Operand::Label(_) => return Err(self.error(EncodeErrorReason::UnresolvedLabel)),
+            _ => return Err(self.error(EncodeErrorReason::InvalidOperands)),
+        };
+
+        if let E::DirectAddress(addr) = eac {
+            self.byte(reg << 3 | 0b110);
+            self.word(*addr);
+            return Ok(());
+        }
+
+        let (base, displacement) = match eac {
+            E::SingleReg(r) => ((*r, None), 0),
+            E::SingleRegPlus(r, d) => ((*r, None), *d),
+            E::Plus(a, b) => ((*a, Some(*b)), 0),
+            E::PlusConstant(a, b, d) => ((*a, Some(*b)), *d),
+            E::DirectAddress(_) => unreachable!(),
+        };
+
+        let rm = match base {
+            (R::BX, Some(R::SI)) => 0b000,
+            (R::BX, Some(R::DI)) => 0b001,
+            (R::BP, Some(R::SI)) => 0b010,
+            (R::BP, Some(R::DI)) => 0b011,
+            (R::SI, None) => 0b100,
+            (R::DI, None) => 0b101,
+            (R::BP, None) => 0b110,
+            (R::BX, None) => 0b111,
+            _ => return Err(self.error(EncodeErrorReason::InvalidOperands)),
+        };
+
+        // [bp] without a displacement is taken by direct addresses, so it gets a zero one
+        if displacement == 0 && rm != 0b110 {
+            self.byte(reg << 3 | rm);
+        } else if let Ok(displacement) = i8::try_from(displacement) {
+            self.byte(0b01000000 | reg << 3 | rm);
+            self.byte(displacement as u8);
+        } else {
+            self.byte(0b10000000 | reg << 3 | rm);
+            self.word(displacement as u16);
+        }
+
+        Ok(())
+    }
+
+    fn encode_prefixes(&mut self) -> Result<(), EncodeError> {
+        if self.i.lock {
+            self.byte(0b11110000);
+        }
+
+        match self.i.repeat {
+            Some(Repeat::Rep) => self.byte(0b11110011),
+            Some(Repeat::Repne) => self.byte(0b11110010),
+            None => {}
+        }
+
+        if let Some(segment) = &self.i.segment_override {
+            let sr = self.segment_register(segment)?;
+            self.byte(0b00100110 | sr << 3);
+        }
+
+        Ok(())
+    }
+
+    fn encode_instruction(&mut self) -> Result<(), EncodeError> {
+        let w = self.w();
+
+        match &self.i.opcode {
+            Opcode::Mov(m) => match m {
+                Mov::RM => self.encode_register_memory_to_from_either(0b10001000),
+                Mov::ImmToReg => {
+                    let reg = self.register(self.destination()?)?;
+                    let imm = self.immediate(self.source()?)?;
+                    self.byte(0b10110000 | w << 3 | reg);
+                    self.data(imm);
+                    Ok(())
+                }
+                Mov::ImmToRegOrMem => self.encode_immediate_to_reg_or_memory(0b11000110, 0b000),
+                Mov::MemToAcc => {
+                    self.accumulator(self.destination()?)?;
+                    self.encode_direct_address(0b10100000 | w, self.source()?)
+                }
+                Mov::AccToMem => {
+                    self.accumulator(self.source()?)?;
+                    self.encode_direct_address(0b10100010 | w, self.destination()?)
+                }
+                Mov::RegOrMemToSeg | Mov::SegToRegOrMem => self.encode_mov_segment_register(),
+            },
+            Opcode::Add(a) => match a {
+                Add::RM => self.encode_register_memory_to_from_either(0b00000000),
+                Add::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b000),
+                Add::ImmToAcc => self.encode_immediate_to_acc(0b00000100),
+            },
+            Opcode::Or(o) => match o {
+                Or::RM => self.encode_register_memory_to_from_either(0b00001000),
+                Or::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b001),
+                Or::ImmToAcc => self.encode_immediate_to_acc(0b00001100),
+            },
+            Opcode::Adc(a) => match a {
+                Adc::RM => self.encode_register_memory_to_from_either(0b00010000),
+                Adc::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b010),
+                Adc::ImmToAcc => self.encode_immediate_to_acc(0b00010100),
+            },
+            Opcode::Sbb(s) => match s {
+                Sbb::RM => self.encode_register_memory_to_from_either(0b00011000),
+                Sbb::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b011),
+                Sbb::ImmToAcc => self.encode_immediate_to_acc(0b00011100),
+            },
+            Opcode::And(a) => match a {
+                And::RM => self.encode_register_memory_to_from_either(0b00100000),
+                And::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b100),
+                And::ImmToAcc => self.encode_immediate_to_acc(0b00100100),
+            },
+            Opcode::Sub(s) => match s {
+                Sub::RM => self.encode_register_memory_to_from_either(0b00101000),
+                Sub::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b101),
+                Sub::ImmToAcc => self.encode_immediate_to_acc(0b00101100),
+            },
+            Opcode::Xor(x) => match x {
+                Xor::RM => self.encode_register_memory_to_from_either(0b00110000),
+                Xor::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b110),
+                Xor::ImmToAcc => self.encode_immediate_to_acc(0b00110100),
+            },
+            Opcode::Cmp(c) => match c {
+                Cmp::RM => self.encode_register_memory_to_from_either(0b00111000),
+                Cmp::ImmToRegOrMem => self.encode_immediate_with_sign_extension(0b111),
+                Cmp::ImmToAcc => self.encode_immediate_to_acc(0b00111100),
+            },
+            Opcode::Test(t) => match t {
+                // test has no D bit, the register is always the source
+                Test::RM => {
+                    let reg = self.register(self.source()?)?;
+                    self.byte(0b10000100 | w);
+                    self.mod_rm(reg, self.destination()?)
+                }
+                Test::ImmToRegOrMem => self.encode_immediate_to_reg_or_memory(0b11110110, 0b000),
+                Test::ImmToAcc => self.encode_immediate_to_acc(0b10101000),
+            },
+            Opcode::Not => self.encode_reg_or_memory(0b11110110 | w, 0b010),
+            Opcode::Neg => self.encode_reg_or_memory(0b11110110 | w, 0b011),
+            Opcode::Mul => self.encode_reg_or_memory(0b11110110 | w, 0b100),
+            Opcode::Imul => self.encode_reg_or_memory(0b11110110 | w, 0b101),
+            Opcode::Div => self.encode_reg_or_memory(0b11110110 | w, 0b110),
+            Opcode::Idiv => self.encode_reg_or_memory(0b11110110 | w, 0b111),
+            Opcode::Inc(i) => match i {
+                Inc::RegOrMem => self.encode_reg_or_memory(0b11111110 | w, 0b000),
+                Inc::Reg => self.encode_register(0b01000000),
+            },
+            Opcode::Dec(d) => match d {
+                Dec::RegOrMem => self.encode_reg_or_memory(0b11111110 | w, 0b001),
+                Dec::Reg => self.encode_register(0b01001000),
+            },
+            Opcode::Shift(s) => {
+                let ext = match s {
+                    Shift::Rol => 0b000,
+                    Shift::Ror => 0b001,
+                    Shift::Rcl => 0b010,
+                    Shift::Rcr => 0b011,
+                    Shift::Shl => 0b100,
+                    Shift::Shr => 0b101,
+                    Shift::Sar => 0b111,
+                };
+
+                // V
+                let v = match self.source()? {
+                    Operand::Immediate(1) => 0,
+                    Operand::Register(Register::CL) => 1,
+                    _ => return Err(self.error(EncodeErrorReason::InvalidOperands)),
+                };
+
+                self.encode_reg_or_memory(0b11010000 | v << 1 | w, ext)
+            }
+            Opcode::Call(c) => match c {
+                Call::Direct => self.encode_near_jump(0b11101000),
+                Call::DirectFar => self.encode_far_pointer(0b10011010),
+                Call::Indirect => self.encode_reg_or_memory(0b11111111, 0b010),
+                Call::IndirectFar => self.encode_reg_or_memory(0b11111111, 0b011),
+            },
+            Opcode::Jmp(j) => match j {
+                Jmp::Short => self.encode_jump(0b11101011),
+                Jmp::Direct => self.encode_near_jump(0b11101001),
+                Jmp::DirectFar => self.encode_far_pointer(0b11101010),
+                Jmp::Indirect => self.encode_reg_or_memory(0b11111111, 0b100),
+                Jmp::IndirectFar => self.encode_reg_or_memory(0b11111111, 0b101),
+            },
+            Opcode::Push(p) => match p {
+                Push::RegOrMem => self.encode_reg_or_memory(0b11111111, 0b110),
+                Push::Reg => self.encode_register(0b01010000),
+                Push::Seg => self.encode_segment_register(0b00000110),
+            },
+            Opcode::Pop(p) => match p {
+                Pop::RegOrMem => self.encode_reg_or_memory(0b10001111, 0b000),
+                Pop::Reg => self.encode_register(0b01011000),
+                Pop::Seg => self.encode_segment_register(0b00000111),
+            },
+            Opcode::Pushf => self.encode_no_operands(0b10011100),
+            Opcode::Popf => self.encode_no_operands(0b10011101),
+            Opcode::Ret(r) => {
+                let opcode = match r {
+                    Ret::Near => 0b11000010,
+                    Ret::Far => 0b11001010,
+                };
+
+                // the forms with the low bit clear pop an extra imm16 bytes off the stack
+                match &self.i.destination {
+                    Some(operand) => {
+                        let imm = self.immediate(operand)?;
+                        self.byte(opcode);
+                        self.word(imm);
+                    }
+                    None => self.byte(opcode | 1),
+                }
+
+                Ok(())
+            }
+            Opcode::Movs => self.encode_no_operands(0b10100100 | w),
+            Opcode::Cmps => self.encode_no_operands(0b10100110 | w),
+            Opcode::Scas => self.encode_no_operands(0b10101110 | w),
+            Opcode::Lods => self.encode_no_operands(0b10101100 | w),
+            Opcode::Stos => self.encode_no_operands(0b10101010 | w),
+            Opcode::Int => {
+                let vector = self.immediate(self.destination()?)?;
+                self.byte(0b11001101);
+                self.byte(vector as u8);
+                Ok(())
+            }
+            Opcode::Int3 => self.encode_no_operands(0b11001100),
+            Opcode::Into => self.encode_no_operands(0b11001110),
+            Opcode::Iret => self.encode_no_operands(0b11001111),
+            Opcode::Xchg(x) => match x {
+                // xchg has no D bit either, the register is always the destination
+                Xchg::RM => {
+                    let reg = self.register(self.destination()?)?;
+                    self.byte(0b10000110 | w);
+                    self.mod_rm(reg, self.source()?)
+                }
+                Xchg::RegWithAcc => {
+                    let reg = self.register(self.source()?)?;
+                    self.byte(0b10010000 | reg);
+                    Ok(())
+                }
+            },
+            Opcode::Xlat => self.encode_no_operands(0b11010111),
+            Opcode::Lea => self.encode_load_address(0b10001101),
+            Opcode::Lds => self.encode_load_address(0b11000101),
+            Opcode::Les => self.encode_load_address(0b11000100),
+            Opcode::Lahf => self.encode_no_operands(0b10011111),
+            Opcode::Sahf => self.encode_no_operands(0b10011110),
+            Opcode::Cbw => self.encode_no_operands(0b10011000),
+            Opcode::Cwd => self.encode_no_operands(0b10011001),
+            Opcode::Daa => self.encode_no_operands(0b00100111),
+            Opcode::Das => self.encode_no_operands(0b00101111),
+            Opcode::Aaa => self.encode_no_operands(0b00110111),
+            Opcode::Aas => self.encode_no_operands(0b00111111),
+            Opcode::Aam => self.encode_ascii_adjust_base(0b11010100),
+            Opcode::Aad => self.encode_ascii_adjust_base(0b11010101),
+            Opcode::Clc => self.encode_no_operands(0b11111000),
+            Opcode::Stc => self.encode_no_operands(0b11111001),
+            Opcode::Cmc => self.encode_no_operands(0b11110101),
+            Opcode::Cld => self.encode_no_operands(0b11111100),
+            Opcode::Std => self.encode_no_operands(0b11111101),
+            Opcode::Cli => self.encode_no_operands(0b11111010),
+            Opcode::Sti => self.encode_no_operands(0b11111011),
+            Opcode::Hlt => self.encode_no_operands(0b11110100),
+            Opcode::Wait => self.encode_no_operands(0b10011011),
+            Opcode::Nop => self.encode_no_operands(0b10010000),
+            Opcode::Esc => {
+                let external_opcode = self.immediate(self.destination()?)? as u8;
+                if external_opcode > 0b111111 {
+                    return Err(self.error(EncodeErrorReason::InvalidOperands));
+                }
+
+                self.byte(0b11011000 | external_opcode >> 3);
+                self.mod_rm(external_opcode & 0b111, self.source()?)
+            }
+            Opcode::In(i) => {
+                let port = self.source()?;
+                self.accumulator(self.destination()?)?;
+                match i {
+                    In::FixedPort => self.encode_fixed_port(0b11100100 | w, port),
+                    In::VariablePort => self.encode_variable_port(0b11101100 | w, port),
+                }
+            }
+            Opcode::Out(o) => {
+                let port = self.destination()?;
+                self.accumulator(self.source()?)?;
+                match o {
+                    Out::FixedPort => self.encode_fixed_port(0b11100110 | w, port),
+                    Out::VariablePort => self.encode_variable_port(0b11101110 | w, port),
+                }
+            }
+            Opcode::J(j) => {
+                let opcode = match j {
+                    J::Jo => 0b01110000,
+                    J::Jno => 0b01110001,
+                    J::Jb => 0b01110010,
+                    J::Jnb => 0b01110011,
+                    J::Je => 0b01110100,
+                    J::Jne => 0b01110101,
+                    J::Jbe => 0b01110110,
+                    J::Ja => 0b01110111,
+                    J::Js => 0b01111000,
+                    J::Jns => 0b01111001,
+                    J::Jp => 0b01111010,
+                    J::Jnp => 0b01111011,
+                    J::Jl => 0b01111100,
+                    J::Jnl => 0b01111101,
+                    J::Jle => 0b01111110,
+                    J::Jg => 0b01111111,
+                    J::Loopnz => 0b11100000,
+                    J::Loopz => 0b11100001,
+                    J::Loop => 0b11100010,
+                    J::Jcxz => 0b11100011,
+                };
+
+                self.encode_jump(opcode)
+            }
+        }
+    }
+
+    /// `opcode` is the form with D and W clear.
+    fn encode_register_memory_to_from_either(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let destination = self.destination()?;
+        let source = self.source()?;
+
+        let reg_is_destination = match (destination, source) {
+            (_, Operand::MemoryAddress(_)) => true,
+            (Operand::MemoryAddress(_), _) => false,
+            // between two registers either works, D is only set if it was when decoded
+            _ => self.i.reg_is_destination.unwrap_or(false),
+        };
+
+        let (d, reg, rm) = match reg_is_destination {
+            true => (1, destination, source),
+            false => (0, source, destination),
+        };
+
+        let reg = self.register(reg)?;
+        self.byte(opcode | d << 1 | self.w());
+        self.mod_rm(reg, rm)
+    }
+
+    fn encode_reg_or_memory(&mut self, opcode: u8, ext: u8) -> Result<(), EncodeError> {
+        self.byte(opcode);
+        self.mod_rm(ext, self.destination()?)
+    }
+
+    /// `opcode` is the form with W clear.
+    fn encode_immediate_to_reg_or_memory(
+        &mut self,
+        opcode: u8,
+        ext: u8,
+    ) -> Result<(), EncodeError> {
+        let imm = self.immediate(self.source()?)?;
+
+        self.byte(opcode | self.w());
+        self.mod_rm(ext, self.destination()?)?;
+        self.data(imm);
+
+        Ok(())
+    }
+
+    fn encode_immediate_with_sign_extension(&mut self, ext: u8) -> Result<(), EncodeError> {
+        let imm = self.immediate(self.source()?)?;
+
+        // S, unless the instruction was decoded with a full word that would have fit
+        let sign_extend =
+            self.i.wide && self.i.sign_extend.unwrap_or(true) && i8::try_from(imm as i16).is_ok();
+
+        self.byte(0b10000000 | (sign_extend as u8) << 1 | self.w());
+        self.mod_rm(ext, self.destination()?)?;
+
+        if sign_extend {
+            self.byte(imm as u8);
+        } else {
+            self.data(imm);
+        }
+
+        Ok(())
+    }
+
+    /// `opcode` is the form with W clear.
+    fn encode_immediate_to_acc(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        self.accumulator(self.destination()?)?;
+        let imm = self.immediate(self.source()?)?;
+
+        self.byte(opcode | self.w());
+        self.data(imm);
+
+        Ok(())
+    }
+
+    fn encode_direct_address(&mut self, opcode: u8, operand: &Operand) -> Result<(), EncodeError> {
+        let Operand::MemoryAddress(EffectiveAddressCalc::DirectAddress(addr)) = operand else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+
+        self.byte(opcode);
+        self.word(*addr);
+
+        Ok(())
+    }
+
+    fn encode_mov_segment_register(&mut self) -> Result<(), EncodeError> {
+        let (opcode, segment, reg_or_memory) = match &self.i.opcode {
+            Opcode::Mov(Mov::RegOrMemToSeg) => (0b10001110, self.destination()?, self.source()?),
+            _ => (0b10001100, self.source()?, self.destination()?),
+        };
+
+        let Operand::Register(segment) = segment else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+        let sr = self.segment_register(segment)?;
+
+        self.byte(opcode);
+        self.mod_rm(sr, reg_or_memory)
+    }
+
+    /// For the forms with the register in the low 3 bits of the opcode.
+    fn encode_register(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let reg = self.register(self.destination()?)?;
+        self.byte(opcode | reg);
+        Ok(())
+    }
+
+    /// `opcode` is the form with SR clear.
+    fn encode_segment_register(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let Operand::Register(segment) = self.destination()? else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+
+        let sr = self.segment_register(segment)?;
+        self.byte(opcode | sr << 3);
+        Ok(())
+    }
+
+    fn encode_no_operands(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        self.byte(opcode);
+        Ok(())
+    }
+
+    fn encode_load_address(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let source = self.source()?;
+        if !matches!(source, Operand::MemoryAddress(_)) {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        }
+
+        let reg = self.register(self.destination()?)?;
+        self.byte(opcode);
+        self.mod_rm(reg, source)
+    }
+
+    fn encode_jump(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        match self.destination()? {
+            Operand::InstPtrIncrement(inc) => {
+                let inc = *inc;
+                self.byte(opcode);
+                self.byte(inc as u8);
+                Ok(())
+            }
+            Operand::Label(_) => Err(self.error(EncodeErrorReason::UnresolvedLabel)),
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    fn encode_near_jump(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        match self.destination()? {
+            Operand::NearInstPtrIncrement(inc) => {
+                let inc = *inc;
+                self.byte(opcode);
+                self.word(inc as u16);
+                Ok(())
+            }
+            Operand::Label(_) => Err(self.error(EncodeErrorReason::UnresolvedLabel)),
+            _ => Err(self.error(EncodeErrorReason::InvalidOperands)),
+        }
+    }
+
+    fn encode_far_pointer(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let Operand::FarPointer(segment, offset) = self.destination()? else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+
+        // the offset comes first, then the segment
+        let (segment, offset) = (*segment, *offset);
+        self.byte(opcode);
+        self.word(offset);
+        self.word(segment);
+
+        Ok(())
+    }
+
+    fn encode_ascii_adjust_base(&mut self, opcode: u8) -> Result<(), EncodeError> {
+        let base = match &self.i.destination {
+            Some(operand) => self.immediate(operand)? as u8,
+            None => 10,
+        };
+
+        self.byte(opcode);
+        self.byte(base);
+
+        Ok(())
+    }
+
+    fn encode_fixed_port(&mut self, opcode: u8, port: &Operand) -> Result<(), EncodeError> {
+        let port = self.immediate(port)?;
+        let Ok(port) = u8::try_from(port) else {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        };
+
+        self.byte(opcode);
+        self.byte(port);
+
+        Ok(())
+    }
+
+    fn encode_variable_port(&mut self, opcode: u8, port: &Operand) -> Result<(), EncodeError> {
+        if !matches!(port, Operand::Register(Register::DX)) {
+            return Err(self.error(EncodeErrorReason::InvalidOperands));
+        }
+
+        self.byte(opcode);
+        Ok(())
+    }
+}
@@ -1,194 +1,977 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 use crate::{
-    decode::Decoder, jump::J, EffectiveAddressCalc, Instruction, Opcode, Operand, Register, Word,
+    call::Call,
+    decode::{DecodeError, Decoder},
+    jmp::Jmp,
+    jump::J,
+    ret::Ret,
+    shift::Shift,
+    EffectiveAddressCalc, Instruction, Opcode, Operand, Register, Repeat, Word,
 };
 
-pub struct Executor<'source> {
+pub struct Executor {
     pub memory: mem::Memory,
-    decoder: Decoder<'source>,
     pub registers: Registers,
+    /// Whatever is listening on the other end of in and out.
+    io_bus: Box<dyn io::IoBus>,
+    /// Physical address just past the last byte of the program, where a program without a hlt
+    /// ends up once it's done.
+    program_end: u32,
+    /// Segment override prefix of the instruction being executed.
+    segment_override: Option<Register>,
+    /// Set by hlt, nothing runs after that since there's no interrupt to wake up from.
+    halted: bool,
 }
 
-impl<'source> Executor<'source> {
-    pub fn new(decoder: Decoder<'source>) -> Self {
-        Self {
-            memory: mem::Memory::new(),
-            registers: Registers::new(),
-            decoder,
+/// What happened to the processor after an instruction ran.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Running,
+    /// hlt was executed and the processor stopped.
+    Halted,
+}
+
+/// The program doesn't fit in the memory between the start of [`LOAD_SEGMENT`] and the end of
+/// the address space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgramTooLarge {
+    /// Size of the program in bytes.
+    pub size: usize,
+    /// How many bytes there are from the load address up to the end of memory.
+    pub available: usize,
+}
+
+impl Display for ProgramTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "program is {:#X} bytes but only {:#X} fit after the load address",
+            self.size, self.available
+        )
+    }
+}
+
+impl std::error::Error for ProgramTooLarge {}
+
+/// Segment the program is loaded into, at offset 0. The start of memory is left to the
+/// interrupt vector table and to the data the program keeps there with DS still at 0.
+pub const LOAD_SEGMENT: u16 = 0x0800;
+
+impl Executor {
+    /// Loads the program at the start of [`LOAD_SEGMENT`], with CS:IP pointing at it.
+    pub fn new(program: &[u8]) -> Result<Self, ProgramTooLarge> {
+        Self::with_io_bus(program, io::LoggingBus)
+    }
+
+    /// Like [`Executor::new`], with `io_bus` answering every in and out the program does.
+    pub fn with_io_bus(
+        program: &[u8],
+        io_bus: impl io::IoBus + 'static,
+    ) -> Result<Self, ProgramTooLarge> {
+        let load_address = physical_address(LOAD_SEGMENT, 0);
+
+        let available = mem::MEMORY_SIZE - load_address as usize;
+        if program.len() > available {
+            return Err(ProgramTooLarge {
+                size: program.len(),
+                available,
+            });
         }
+
+        let mut memory = mem::Memory::new();
+        memory.store_bytes(load_address, program);
+
+        let mut registers = Registers::new();
+        registers.set(&Register::CS, LOAD_SEGMENT);
+
+        Ok(Self {
+            memory,
+            registers,
+            io_bus: Box::new(io_bus),
+            program_end: load_address + program.len() as u32,
+            segment_override: None,
+            halted: false,
+        })
     }
 
-    fn eval_operand(&mut self, operand: &Operand) -> Word {
-        let value = match operand {
-            Operand::Immediate(imm) => (*imm).into(),
-            Operand::Register(reg) => *self.registers.get_reg(reg),
+    /// Physical address of the instruction at CS:IP, the one [`Executor::execute_next`] runs.
+    pub fn next_address(&self) -> u32 {
+        physical_address(self.registers.get(&Register::CS), self.registers.ip.into())
+    }
+
+    fn eval_operand(&mut self, operand: &Operand, wide: bool) -> u16 {
+        match operand {
+            Operand::Immediate(imm) => *imm,
+            Operand::Register(reg) => self.registers.get(reg),
             Operand::MemoryAddress(eac) => {
                 let addr = self.resolve_eac(eac);
-                self.memory.load(addr)
+                self.load(addr, wide)
             }
-            Operand::ByteImmediate(imm) => Word::new(0, *imm),
-            Operand::WordImmediate(imm) => (*imm).into(),
-            Operand::InstPtrIncrement(_) => todo!(),
+            Operand::ByteImmediate(imm) => *imm as u16,
+            Operand::WordImmediate(imm) => *imm,
+            Operand::InstPtrIncrement(_)
+            | Operand::NearInstPtrIncrement(_)
+            | Operand::FarPointer(_, _)
+            | Operand::Label(_) => unreachable!("{:?} is only a jump target", operand),
+        }
+    }
+
+    /// Runs the instruction at CS:IP. Decode errors are reported with the physical address
+    /// of the instruction as their offset.
+    pub fn execute_next(
+        &mut self,
+    ) -> Result<Option<(Instruction, RegistersDiff, Status)>, DecodeError> {
+        let ip = u16::from(self.registers.ip);
+        let addr = self.next_address();
+
+        // execution stops after a hlt, or once IP leaves the program in the segment it was
+        // loaded into. Code the program jumped to through another segment, like a handler it
+        // put somewhere else, runs for as long as it likes.
+        let in_load_segment = self.registers.get(&Register::CS) == LOAD_SEGMENT;
+        if self.halted || (in_load_segment && addr >= self.program_end) {
+            return Ok(None);
+        }
+
+        let Some(decoded) = Decoder::new(self.memory.bytes_from(addr)).next() else {
+            return Ok(None);
+        };
+        let decoded = decoded.map_err(|mut err| {
+            err.offset += addr as usize;
+            err
+        })?;
+        let len = decoded.encoded_len() as u16;
+        let i = decoded.instruction;
+
+        let before = self.registers;
+        self.registers.ip = ip.wrapping_add(len).into();
+        self.segment_override = i.segment_override;
+        match &i.opcode {
+            Opcode::Mov(_) => self.execute_mov(&i),
+            Opcode::Add(_) | Opcode::Adc(_) | Opcode::Sub(_) | Opcode::Sbb(_) | Opcode::Cmp(_) => {
+                self.execute_arithmetic(&i)
+            }
+            Opcode::And(_) | Opcode::Or(_) | Opcode::Xor(_) | Opcode::Test(_) => {
+                self.execute_logical(&i)
+            }
+            Opcode::Not => self.execute_not(&i),
+            Opcode::Neg => self.execute_neg(&i),
+            Opcode::Mul | Opcode::Imul => self.execute_mul(&i),
+            Opcode::Div | Opcode::Idiv => self.execute_div(&i),
+            Opcode::Inc(_) | Opcode::Dec(_) => self.execute_inc_dec(&i),
+            Opcode::Shift(_) => self.execute_shift(&i),
+            Opcode::Call(_) => self.execute_call(&i),
+            Opcode::Jmp(_) => self.execute_jmp(&i),
+            Opcode::Push(_) => self.execute_push(&i),
+            Opcode::Pop(_) => self.execute_pop(&i),
+            Opcode::Pushf => self.push(self.registers.flags.into()),
+            Opcode::Popf => self.registers.flags = self.pop().into(),
+            Opcode::Ret(_) => self.execute_ret(&i),
+            Opcode::Movs | Opcode::Cmps | Opcode::Scas | Opcode::Lods | Opcode::Stos => {
+                self.execute_string(&i)
+            }
+            Opcode::Int => {
+                let vector = self.eval_operand(i.destination(), false);
+                self.interrupt(vector as u8)
+            }
+            Opcode::Int3 => self.interrupt(BREAKPOINT),
+            Opcode::Into => {
+                if self.registers.flags.overflow {
+                    self.interrupt(OVERFLOW_TRAP)
+                }
+            }
+            Opcode::Iret => self.execute_iret(),
+            Opcode::Xchg(_) => self.execute_xchg(&i),
+            Opcode::Xlat => self.execute_xlat(),
+            Opcode::Lea => self.execute_lea(&i),
+            Opcode::Lds | Opcode::Les => self.execute_load_far_pointer(&i),
+            Opcode::Lahf => {
+                let flags = u16::from(self.registers.flags);
+                self.registers.set(&Register::AH, flags);
+            }
+            Opcode::Sahf => {
+                // only the low byte is replaced, that's where the arithmetic flags are
+                let flags = u16::from(self.registers.flags) & 0xff00;
+                let ah = self.registers.get(&Register::AH);
+                self.registers.flags = (flags | ah).into();
+            }
+            Opcode::Cbw => {
+                let al = self.registers.get(&Register::AL);
+                self.registers
+                    .set(&Register::AX, al as u8 as i8 as i16 as u16);
+            }
+            Opcode::Cwd => {
+                let ax = self.registers.get(&Register::AX);
+                let dx = if ax & 0x8000 != 0 { 0xffff } else { 0 };
+                self.registers.set(&Register::DX, dx);
+            }
+            Opcode::Daa | Opcode::Das => self.execute_decimal_adjust(&i),
+            Opcode::Aaa | Opcode::Aas => self.execute_ascii_adjust(&i),
+            Opcode::Aam | Opcode::Aad => self.execute_ascii_adjust_base(&i),
+            Opcode::Clc => self.registers.flags.carry = false,
+            Opcode::Stc => self.registers.flags.carry = true,
+            Opcode::Cmc => self.registers.flags.carry = !self.registers.flags.carry,
+            Opcode::Cld => self.registers.flags.direction = false,
+            Opcode::Std => self.registers.flags.direction = true,
+            Opcode::Cli => self.registers.flags.interrupt = false,
+            Opcode::Sti => self.registers.flags.interrupt = true,
+            Opcode::Hlt => self.halted = true,
+            // there's no coprocessor to wait for or hand the escape to, and no other bus
+            // master to lock out
+            Opcode::Wait | Opcode::Esc | Opcode::Nop => {}
+            Opcode::In(_) => self.execute_in(&i),
+            Opcode::Out(_) => self.execute_out(&i),
+            Opcode::J(_) => self.execute_jump(&i),
+        };
+
+        let status = if self.halted {
+            Status::Halted
+        } else {
+            Status::Running
         };
 
-        value
+        Ok(Some((i, RegistersDiff(before, self.registers), status)))
     }
 
-    pub fn execute_next(&mut self) -> Option<(Instruction, RegistersDiff)> {
-        self.decoder.decode_next().map(|i| {
-            let before = self.registers;
-            self.registers.ip = (self.decoder.read_offset as u16).into();
-            match &i.opcode {
-                Opcode::Mov(_) => self.execute_mov(&i),
-                Opcode::Add(_) => self.execute_add(&i),
-                Opcode::Sub(_) => self.execute_sub(&i),
-                Opcode::Cmp(_) => self.execute_cmp(&i),
-                Opcode::J(_) => self.execute_jump(&i),
+    fn execute_arithmetic(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("arithmetic to have a source");
+        let wide = i.wide;
+        let source_value = self.eval_operand(source, wide);
+        let dest = self.eval_operand(i.destination(), wide);
+
+        let flags = &mut self.registers.flags;
+        let carry_in = flags.carry as u16;
+
+        let result = match &i.opcode {
+            Opcode::Add(_) => flags.add(dest, source_value, 0, wide),
+            Opcode::Adc(_) => flags.add(dest, source_value, carry_in, wide),
+            Opcode::Sub(_) | Opcode::Cmp(_) => flags.sub(dest, source_value, 0, wide),
+            Opcode::Sbb(_) => flags.sub(dest, source_value, carry_in, wide),
+            _ => unreachable!(),
+        };
+
+        // cmp is a sub that only keeps the flags
+        if !matches!(i.opcode, Opcode::Cmp(_)) {
+            self.store_operand(i.destination(), result, wide);
+        }
+    }
+
+    fn execute_mov(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("movs to have a source operand");
+        let wide = i.wide;
+
+        let value = self.eval_operand(source, wide);
+        self.store_operand(i.destination(), value, wide);
+    }
+
+    fn execute_logical(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("logic ops to have a source");
+        let wide = i.wide;
+        let source_value = self.eval_operand(source, wide);
+        let dest = self.eval_operand(i.destination(), wide);
+
+        let result = match &i.opcode {
+            Opcode::And(_) | Opcode::Test(_) => dest & source_value,
+            Opcode::Or(_) => dest | source_value,
+            Opcode::Xor(_) => dest ^ source_value,
+            _ => unreachable!(),
+        };
+
+        // test only updates the flags, the destination is left alone
+        if !matches!(i.opcode, Opcode::Test(_)) {
+            self.store_operand(i.destination(), result, wide);
+        }
+
+        let flags = &mut self.registers.flags;
+        flags.carry = false;
+        flags.overflow = false;
+        flags.set_result(result, wide);
+    }
+
+    fn execute_not(&mut self, i: &Instruction) {
+        let wide = i.wide;
+        let value = self.eval_operand(i.destination(), wide);
+        self.store_operand(i.destination(), !value, wide);
+    }
+
+    fn execute_neg(&mut self, i: &Instruction) {
+        let wide = i.wide;
+        let value = self.eval_operand(i.destination(), wide);
+        let result = self.registers.flags.sub(0, value, 0, wide);
+        self.store_operand(i.destination(), result, wide);
+    }
+
+    fn execute_mul(&mut self, i: &Instruction) {
+        let signed = matches!(i.opcode, Opcode::Imul);
+        let wide = i.wide;
+        let operand = self.eval_operand(i.destination(), wide);
+        let ax = self.registers.get(&Register::AX);
+
+        // the upper half of the product is only significant if it isn't just the sign extension
+        // (or zero extension) of the lower half
+        let upper_half_is_significant = if wide {
+            let (product, significant) = if signed {
+                let product = ax as i16 as i32 * operand as i16 as i32;
+                (product as u32, product != product as i16 as i32)
+            } else {
+                let product = ax as u32 * operand as u32;
+                (product, product > 0xffff)
             };
-            (i, RegistersDiff(before, self.registers))
-        })
+
+            self.registers.set(&Register::AX, product as u16);
+            self.registers.set(&Register::DX, (product >> 16) as u16);
+            significant
+        } else {
+            let al = ax as u8;
+            let (product, significant) = if signed {
+                let product = al as i8 as i16 * operand as i8 as i16;
+                (product as u16, product != product as i8 as i16)
+            } else {
+                let product = al as u16 * operand;
+                (product, product > 0xff)
+            };
+
+            self.registers.set(&Register::AX, product);
+            significant
+        };
+
+        let flags = &mut self.registers.flags;
+        flags.carry = upper_half_is_significant;
+        flags.overflow = upper_half_is_significant;
     }
 
-    fn execute_add(&mut self, i: &Instruction) {
-        let source = i.source.as_ref().expect("add to have a source operand");
-        let source_value: u16 = self.eval_operand(source).into();
+    fn execute_div(&mut self, i: &Instruction) {
+        let signed = matches!(i.opcode, Opcode::Idiv);
+        let wide = i.wide;
+        let divisor = self.eval_operand(i.destination(), wide);
+        let ax = self.registers.get(&Register::AX);
+
+        if wide {
+            let dx = self.registers.get(&Register::DX);
+            let dividend = ((dx as u32) << 16) | ax as u32;
 
-        match &i.destination {
-            Operand::Register(reg) => {
-                let reg_value = self.registers.get_reg(reg);
-                let dest: u16 = reg_value.into();
-                let result = dest + source_value;
-                self.registers.set(reg, result);
-                let msb_is_1 = (0x8000 & result) != 0;
+            let result = if signed {
+                let (dividend, divisor) = (dividend as i32, divisor as i16 as i32);
+                dividend
+                    .checked_div(divisor)
+                    .filter(|q| (-0x7fff..=0x7fff).contains(q))
+                    .map(|q| (q as u16, (dividend % divisor) as u16))
+            } else {
+                let divisor = divisor as u32;
+                dividend
+                    .checked_div(divisor)
+                    .filter(|q| *q <= 0xffff)
+                    .map(|q| (q as u16, (dividend % divisor) as u16))
+            };
 
-                self.registers.flags.sign = msb_is_1;
-                self.registers.flags.zero = result == 0;
+            match result {
+                Some((quotient, remainder)) => {
+                    self.registers.set(&Register::AX, quotient);
+                    self.registers.set(&Register::DX, remainder);
+                }
+                None => self.interrupt(DIVIDE_ERROR),
             }
-            Operand::MemoryAddress(eac) => {
-                let dest: u16 = self.eval_operand(&i.destination).into();
-                let result = dest + source_value;
-                let addr = self.resolve_eac(eac);
-                self.memory.store(addr, result)
+        } else {
+            let result = if signed {
+                let (dividend, divisor) = (ax as i16, divisor as i8 as i16);
+                dividend
+                    .checked_div(divisor)
+                    .filter(|q| (-0x7f..=0x7f).contains(q))
+                    .map(|q| (q as u8, (dividend % divisor) as u8))
+            } else {
+                ax.checked_div(divisor)
+                    .filter(|q| *q <= 0xff)
+                    .map(|q| (q as u8, (ax % divisor) as u8))
+            };
+
+            match result {
+                Some((quotient, remainder)) => {
+                    self.registers.set(&Register::AL, quotient as u16);
+                    self.registers.set(&Register::AH, remainder as u16);
+                }
+                None => self.interrupt(DIVIDE_ERROR),
             }
-            Operand::Immediate(_) => todo!(),
-            Operand::ByteImmediate(_) => todo!(),
-            Operand::WordImmediate(_) => todo!(),
-            Operand::InstPtrIncrement(_) => todo!(),
         }
     }
 
-    fn execute_sub(&mut self, i: &Instruction) {
-        let source = i.source.as_ref().expect("sub to have a source operand");
-        let source_value: u16 = self.eval_operand(source).into();
+    fn execute_inc_dec(&mut self, i: &Instruction) {
+        let wide = i.wide;
+        let value = self.eval_operand(i.destination(), wide);
 
-        match &i.destination {
-            Operand::Register(reg) => {
-                let reg_value = self.registers.get_reg(reg);
-                let dest: u16 = reg_value.into();
-                let result = dest.wrapping_sub(source_value);
-                self.registers.set(reg, result);
-                let msb_is_1 = (0x8000 & result) != 0;
+        // unlike add and sub, the carry flag is left as is
+        let flags = &mut self.registers.flags;
+        let carry = flags.carry;
 
-                self.registers.flags.sign = msb_is_1;
-                self.registers.flags.zero = result == 0;
+        let result = match &i.opcode {
+            Opcode::Inc(_) => flags.add(value, 1, 0, wide),
+            Opcode::Dec(_) => flags.sub(value, 1, 0, wide),
+            _ => unreachable!(),
+        };
+
+        flags.carry = carry;
+        self.store_operand(i.destination(), result, wide);
+    }
+
+    fn execute_call(&mut self, i: &Instruction) {
+        let Opcode::Call(call) = &i.opcode else {
+            unreachable!()
+        };
+
+        match call {
+            Call::Direct | Call::Indirect => {
+                let target = self.branch_target(i);
+                self.push(self.registers.ip.into());
+                self.jump_to(target);
+            }
+            Call::DirectFar | Call::IndirectFar => {
+                let (segment, offset) = self.far_branch_target(i);
+                self.push(self.registers.cs.into());
+                self.push(self.registers.ip.into());
+                self.far_jump_to(segment, offset);
             }
-            Operand::MemoryAddress(_) => todo!(),
-            Operand::Immediate(_) => todo!(),
-            Operand::ByteImmediate(_) => todo!(),
-            Operand::WordImmediate(_) => todo!(),
-            Operand::InstPtrIncrement(_) => todo!(),
         }
     }
 
-    fn execute_cmp(&mut self, i: &Instruction) {
-        let source = i.source.as_ref().expect("sub to have a source operand");
-        let source_value: u16 = self.eval_operand(source).into();
-
-        match &i.destination {
-            Operand::Register(reg) => {
-                let reg_value = self.registers.get_reg(reg);
-                let dest: u16 = reg_value.into();
-                let result = dest.wrapping_sub(source_value);
-                let msb_is_1 = (0x8000 & result) != 0;
+    fn execute_jmp(&mut self, i: &Instruction) {
+        let Opcode::Jmp(jmp) = &i.opcode else {
+            unreachable!()
+        };
 
-                self.registers.flags.sign = msb_is_1;
-                self.registers.flags.zero = result == 0;
+        match jmp {
+            Jmp::Short | Jmp::Direct | Jmp::Indirect => {
+                let target = self.branch_target(i);
+                self.jump_to(target);
+            }
+            Jmp::DirectFar | Jmp::IndirectFar => {
+                let (segment, offset) = self.far_branch_target(i);
+                self.far_jump_to(segment, offset);
             }
-            Operand::MemoryAddress(_) => todo!(),
-            Operand::Immediate(_) => todo!(),
-            Operand::ByteImmediate(_) => todo!(),
-            Operand::WordImmediate(_) => todo!(),
-            Operand::InstPtrIncrement(_) => todo!(),
         }
     }
 
-    fn execute_mov(&mut self, i: &Instruction) {
-        let source = i.source.as_ref().expect("movs to have a source operand");
+    fn execute_ret(&mut self, i: &Instruction) {
+        let ip = self.pop();
+
+        if let Opcode::Ret(Ret::Far) = &i.opcode {
+            self.registers.cs = self.pop().into();
+        }
+
+        // ret imm16 also discards the arguments the caller pushed
+        if let Some(Operand::Immediate(bytes)) = &i.destination {
+            let sp = self.registers.get(&Register::SP).wrapping_add(*bytes);
+            self.registers.set(&Register::SP, sp);
+        }
 
-        let value = self.eval_operand(source);
+        self.jump_to(ip);
+    }
+
+    /// Runs a string instruction once, or as long as its repeat prefix says to.
+    fn execute_string(&mut self, i: &Instruction) {
+        let wide = i.wide;
+        let accumulator = if wide { Register::AX } else { Register::AL };
 
-        match &i.destination {
-            Operand::Register(reg) => {
-                self.registers.set(reg, value.into());
+        loop {
+            if i.repeat.is_some() && self.registers.get(&Register::CX) == 0 {
+                break;
             }
+
+            // the source can be moved out of DS with an override, the destination is always ES:DI
+            let segment = self.segment_override.unwrap_or(Register::DS);
+            let source = physical_address(
+                self.registers.get(&segment),
+                self.registers.get(&Register::SI),
+            );
+            let destination = physical_address(
+                self.registers.get(&Register::ES),
+                self.registers.get(&Register::DI),
+            );
+
+            let (uses_si, uses_di) = match &i.opcode {
+                Opcode::Movs => {
+                    let value = self.load(source, wide);
+                    self.store(destination, value, wide);
+                    (true, true)
+                }
+                Opcode::Cmps => {
+                    let a = self.load(source, wide);
+                    let b = self.load(destination, wide);
+                    self.registers.flags.sub(a, b, 0, wide);
+                    (true, true)
+                }
+                Opcode::Scas => {
+                    let a = self.registers.get(&accumulator);
+                    let b = self.load(destination, wide);
+                    self.registers.flags.sub(a, b, 0, wide);
+                    (false, true)
+                }
+                Opcode::Lods => {
+                    let value = self.load(source, wide);
+                    self.registers.set(&accumulator, value);
+                    (true, false)
+                }
+                Opcode::Stos => {
+                    let value = self.registers.get(&accumulator);
+                    self.store(destination, value, wide);
+                    (false, true)
+                }
+                _ => unreachable!(),
+            };
+
+            // the direction flag makes si and di count down instead of up
+            let size = if wide { 2 } else { 1 };
+            let delta = if self.registers.flags.direction {
+                0u16.wrapping_sub(size)
+            } else {
+                size
+            };
+
+            for (used, reg) in [(uses_si, Register::SI), (uses_di, Register::DI)] {
+                if used {
+                    let value = self.registers.get(&reg).wrapping_add(delta);
+                    self.registers.set(&reg, value);
+                }
+            }
+
+            let Some(repeat) = i.repeat else {
+                break;
+            };
+
+            let cx = self.registers.get(&Register::CX).wrapping_sub(1);
+            self.registers.set(&Register::CX, cx);
+
+            // only cmps and scas look at the zero flag to stop early
+            if matches!(i.opcode, Opcode::Cmps | Opcode::Scas) {
+                let zero = self.registers.flags.zero;
+                match repeat {
+                    Repeat::Rep if !zero => break,
+                    Repeat::Repne if zero => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Offset within the code segment that a near jump or call transfers control to.
+    fn branch_target(&mut self, i: &Instruction) -> u16 {
+        let ip = u16::from(self.registers.ip);
+
+        match i.destination() {
+            Operand::InstPtrIncrement(inc) => ip.wrapping_add(*inc as u16),
+            Operand::NearInstPtrIncrement(inc) => ip.wrapping_add(*inc as u16),
+            operand => self.eval_operand(operand, true),
+        }
+    }
+
+    /// Segment and offset that a far jump or call transfers control to.
+    fn far_branch_target(&mut self, i: &Instruction) -> (u16, u16) {
+        match i.destination() {
+            Operand::FarPointer(segment, offset) => (*segment, *offset),
             Operand::MemoryAddress(eac) => {
+                // the offset is stored first, then the segment
                 let addr = self.resolve_eac(eac);
-                self.memory.store(addr, value)
+                let offset = self.memory.load(addr).into();
+                let segment = self.memory.load(addr + 2).into();
+                (segment, offset)
             }
-            Operand::Immediate(_) => todo!(),
-            Operand::ByteImmediate(_) => todo!(),
-            Operand::WordImmediate(_) => todo!(),
-            Operand::InstPtrIncrement(_) => todo!(),
+            operand => unreachable!("far pointers can't be in {:?}", operand),
         }
     }
 
-    fn execute_jump(&mut self, i: &Instruction) {
-        let inc = match &i.destination {
-            Operand::InstPtrIncrement(inc) => *inc,
+    fn execute_push(&mut self, i: &Instruction) {
+        let value = match i.destination() {
+            // the 8086 pushes the value sp has after it's been decremented
+            Operand::Register(Register::SP) => self.registers.get(&Register::SP).wrapping_sub(2),
+            operand => self.eval_operand(operand, i.wide),
+        };
+        self.push(value);
+    }
+
+    fn execute_pop(&mut self, i: &Instruction) {
+        let value = self.pop();
+        self.store_operand(i.destination(), value, i.wide);
+    }
+
+    /// Pushes FLAGS, CS and IP and transfers control through the interrupt vector table,
+    /// which lives at the very start of memory with 4 bytes (IP then CS) per interrupt type.
+    fn interrupt(&mut self, vector: u8) {
+        self.push(self.registers.flags.into());
+        self.push(self.registers.cs.into());
+        self.push(self.registers.ip.into());
+
+        // handlers start with interrupts disabled and single stepping off
+        self.registers.flags.interrupt = false;
+        self.registers.flags.trap = false;
+
+        let entry = vector as u32 * 4;
+        let ip = self.memory.load(entry).into();
+        let cs = self.memory.load(entry + 2).into();
+        self.far_jump_to(cs, ip);
+    }
+
+    fn execute_xchg(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("xchg to have a source");
+        let wide = i.wide;
+
+        let a = self.eval_operand(i.destination(), wide);
+        let b = self.eval_operand(source, wide);
+        self.store_operand(i.destination(), b, wide);
+        self.store_operand(source, a, wide);
+    }
+
+    /// Looks al up in the 256 byte table at bx.
+    fn execute_xlat(&mut self) {
+        let segment = self.segment_override.unwrap_or(Register::DS);
+        let offset = self
+            .registers
+            .get(&Register::BX)
+            .wrapping_add(self.registers.get(&Register::AL));
+
+        let addr = physical_address(self.registers.get(&segment), offset);
+        let value = self.memory.load_byte(addr);
+        self.registers.set(&Register::AL, value as u16);
+    }
+
+    fn execute_lea(&mut self, i: &Instruction) {
+        let Some(Operand::MemoryAddress(eac)) = &i.source else {
+            unreachable!("lea to have a memory source")
+        };
+
+        let offset = self.effective_address(eac);
+        self.store_operand(i.destination(), offset, true);
+    }
+
+    /// lds and les load a far pointer, the offset into the destination and the segment into
+    /// ds or es.
+    fn execute_load_far_pointer(&mut self, i: &Instruction) {
+        let Some(Operand::MemoryAddress(eac)) = &i.source else {
+            unreachable!("{:?} to have a memory source", i.opcode)
+        };
+
+        let addr = self.resolve_eac(eac);
+        let offset = self.memory.load(addr).into();
+        let segment = self.memory.load(addr + 2).into();
+
+        let segment_register = match &i.opcode {
+            Opcode::Lds => Register::DS,
+            Opcode::Les => Register::ES,
             _ => unreachable!(),
         };
 
-        let new_offset = (u16::from(self.registers.ip) as i16 + inc as i16) as u16;
+        self.store_operand(i.destination(), offset, true);
+        self.registers.set(&segment_register, segment);
+    }
 
-        match &i.opcode {
-            Opcode::J(J::Jne) => {
-                if !self.registers.flags.zero {
-                    self.decoder.read_offset = new_offset as usize;
-                    self.registers.ip = Word::from(new_offset);
+    /// Fixes up al after adding or subtracting two packed BCD bytes.
+    fn execute_decimal_adjust(&mut self, i: &Instruction) {
+        let old_al = self.registers.get(&Register::AL) as u8;
+        let flags = &mut self.registers.flags;
+        let old_carry = flags.carry;
+        let mut al = old_al;
+
+        flags.carry = false;
+
+        if (al & 0x0f) > 9 || flags.auxiliary_carry {
+            let (adjusted, carry) = match &i.opcode {
+                Opcode::Daa => al.overflowing_add(6),
+                Opcode::Das => al.overflowing_sub(6),
+                _ => unreachable!(),
+            };
+            al = adjusted;
+            flags.carry = old_carry || carry;
+            flags.auxiliary_carry = true;
+        } else {
+            flags.auxiliary_carry = false;
+        }
+
+        if old_al > 0x99 || old_carry {
+            al = match &i.opcode {
+                Opcode::Daa => al.wrapping_add(0x60),
+                Opcode::Das => al.wrapping_sub(0x60),
+                _ => unreachable!(),
+            };
+            flags.carry = true;
+        } else if matches!(i.opcode, Opcode::Daa) {
+            flags.carry = false;
+        }
+
+        flags.set_result(al as u16, false);
+        self.registers.set(&Register::AL, al as u16);
+    }
+
+    /// Fixes up al after adding or subtracting two unpacked BCD digits, carrying into ah.
+    fn execute_ascii_adjust(&mut self, i: &Instruction) {
+        let mut al = self.registers.get(&Register::AL) as u8;
+        let mut ah = self.registers.get(&Register::AH) as u8;
+        let flags = &mut self.registers.flags;
+
+        let adjust = (al & 0x0f) > 9 || flags.auxiliary_carry;
+
+        if adjust {
+            (al, ah) = match &i.opcode {
+                Opcode::Aaa => (al.wrapping_add(6), ah.wrapping_add(1)),
+                Opcode::Aas => (al.wrapping_sub(6), ah.wrapping_sub(1)),
+                _ => unreachable!(),
+            };
+        }
+
+        flags.auxiliary_carry = adjust;
+        flags.carry = adjust;
+
+        self.registers.set(&Register::AL, (al & 0x0f) as u16);
+        self.registers.set(&Register::AH, ah as u16);
+    }
+
+    /// aam splits al into two unpacked digits after a multiply, aad joins them before a divide.
+    fn execute_ascii_adjust_base(&mut self, i: &Instruction) {
+        let base = match &i.destination {
+            Some(operand) => self.eval_operand(operand, false) as u8,
+            None => 10,
+        };
+
+        let al = self.registers.get(&Register::AL) as u8;
+        let ah = self.registers.get(&Register::AH) as u8;
+
+        let (al, ah) = match &i.opcode {
+            Opcode::Aam => {
+                // aam divides, so a zero base is a divide error just like div
+                if base == 0 {
+                    self.interrupt(DIVIDE_ERROR);
+                    return;
+                }
+                (al % base, al / base)
+            }
+            Opcode::Aad => (al.wrapping_add(ah.wrapping_mul(base)), 0),
+            _ => unreachable!(),
+        };
+
+        self.registers.flags.set_result(al as u16, false);
+        self.registers.set(&Register::AL, al as u16);
+        self.registers.set(&Register::AH, ah as u16);
+    }
+
+    fn execute_in(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("in to have a port");
+        let port = self.eval_operand(source, true);
+        let value = self.io_bus.read(port, i.wide);
+        self.store_operand(i.destination(), value, i.wide);
+    }
+
+    fn execute_out(&mut self, i: &Instruction) {
+        let source = i.source.as_ref().expect("out to have an accumulator");
+        let port = self.eval_operand(i.destination(), true);
+        let value = self.eval_operand(source, i.wide);
+        self.io_bus.write(port, value, i.wide);
+    }
+
+    fn execute_iret(&mut self) {
+        let ip = self.pop();
+        let cs = self.pop();
+        self.registers.flags = self.pop().into();
+        self.far_jump_to(cs, ip);
+    }
+
+    fn push(&mut self, value: u16) {
+        let sp = u16::from(self.registers.sp).wrapping_sub(2);
+        self.registers.sp = sp.into();
+        let addr = physical_address(self.registers.get(&Register::SS), sp);
+        self.memory.store(addr, value);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let sp = u16::from(self.registers.sp);
+        let addr = physical_address(self.registers.get(&Register::SS), sp);
+        self.registers.sp = sp.wrapping_add(2).into();
+        self.memory.load(addr).into()
+    }
+
+    fn jump_to(&mut self, offset: u16) {
+        self.registers.ip = Word::from(offset);
+    }
+
+    fn far_jump_to(&mut self, segment: u16, offset: u16) {
+        self.registers.cs = Word::from(segment);
+        self.jump_to(offset);
+    }
+
+    fn execute_shift(&mut self, i: &Instruction) {
+        let Opcode::Shift(shift) = &i.opcode else {
+            unreachable!()
+        };
+
+        let count = i.source.as_ref().expect("shifts to have a count operand");
+        let count = self.eval_operand(count, false) as u8;
+
+        // a zero count leaves both the operand and the flags untouched
+        if count == 0 {
+            return;
+        }
+
+        let wide = i.wide;
+        let (mask, sign_bit) = width_masks(wide);
+        let mut value = self.eval_operand(i.destination(), wide);
+        let flags = &mut self.registers.flags;
+
+        for _ in 0..count {
+            let msb = (sign_bit & value) != 0;
+            let lsb = (1 & value) != 0;
+
+            value = match shift {
+                Shift::Rol => {
+                    flags.carry = msb;
+                    ((value << 1) | msb as u16) & mask
+                }
+                Shift::Ror => {
+                    flags.carry = lsb;
+                    (value >> 1) | if lsb { sign_bit } else { 0 }
+                }
+                Shift::Rcl => {
+                    let carry_in = flags.carry as u16;
+                    flags.carry = msb;
+                    ((value << 1) | carry_in) & mask
+                }
+                Shift::Rcr => {
+                    let carry_in = flags.carry;
+                    flags.carry = lsb;
+                    (value >> 1) | if carry_in { sign_bit } else { 0 }
+                }
+                Shift::Shl => {
+                    flags.carry = msb;
+                    (value << 1) & mask
                 }
+                Shift::Shr => {
+                    flags.carry = lsb;
+                    value >> 1
+                }
+                Shift::Sar => {
+                    flags.carry = lsb;
+                    (value >> 1) | (value & sign_bit)
+                }
+            };
+
+            // only defined for single bit shifts, but this is what the 8086 ends up with
+            let new_msb = (sign_bit & value) != 0;
+            flags.overflow = match shift {
+                Shift::Rol | Shift::Rcl | Shift::Shl => new_msb != flags.carry,
+                Shift::Ror | Shift::Rcr => new_msb != ((sign_bit >> 1) & value != 0),
+                Shift::Shr => msb,
+                Shift::Sar => false,
+            };
+        }
+
+        // rotates leave the result flags alone
+        if matches!(shift, Shift::Shl | Shift::Shr | Shift::Sar) {
+            flags.set_result(value, wide);
+        }
+
+        self.store_operand(i.destination(), value, wide);
+    }
+
+    fn load(&mut self, addr: u32, wide: bool) -> u16 {
+        if wide {
+            self.memory.load(addr).into()
+        } else {
+            self.memory.load_byte(addr) as u16
+        }
+    }
+
+    fn store(&mut self, addr: u32, value: u16, wide: bool) {
+        if wide {
+            self.memory.store(addr, value)
+        } else {
+            self.memory.store_byte(addr, value as u8)
+        }
+    }
+
+    fn store_operand(&mut self, operand: &Operand, value: u16, wide: bool) {
+        match operand {
+            Operand::Register(reg) => self.registers.set(reg, value),
+            Operand::MemoryAddress(eac) => {
+                let addr = self.resolve_eac(eac);
+                self.store(addr, value, wide)
             }
-            _ => todo!(),
+            _ => unreachable!("can't store into {:?}", operand),
         }
     }
 
-    fn resolve_eac(&mut self, eac: &EffectiveAddressCalc) -> u16 {
-        let addr = match eac {
-            EffectiveAddressCalc::SingleReg(reg) => {
-                let addr: u16 = self.registers.get_reg(reg).into();
-                addr
+    fn execute_jump(&mut self, i: &Instruction) {
+        let new_offset = self.branch_target(i);
+
+        let Opcode::J(j) = &i.opcode else {
+            unreachable!()
+        };
+
+        let flags = self.registers.flags;
+
+        let should_jump = match j {
+            J::Je => flags.zero,
+            J::Jl => flags.sign != flags.overflow,
+            J::Jle => flags.zero || flags.sign != flags.overflow,
+            J::Jb => flags.carry,
+            J::Jbe => flags.carry || flags.zero,
+            J::Jp => flags.parity,
+            J::Jo => flags.overflow,
+            J::Js => flags.sign,
+            J::Jne => !flags.zero,
+            J::Jnl => flags.sign == flags.overflow,
+            J::Jg => !flags.zero && flags.sign == flags.overflow,
+            J::Jnb => !flags.carry,
+            J::Ja => !flags.carry && !flags.zero,
+            J::Jnp => !flags.parity,
+            J::Jno => !flags.overflow,
+            J::Jns => !flags.sign,
+            J::Loop | J::Loopz | J::Loopnz => {
+                // loops count down cx without touching any of the flags
+                let cx = self.registers.get(&Register::CX).wrapping_sub(1);
+                self.registers.set(&Register::CX, cx);
+
+                match j {
+                    J::Loopz => cx != 0 && flags.zero,
+                    J::Loopnz => cx != 0 && !flags.zero,
+                    _ => cx != 0,
+                }
             }
+            J::Jcxz => self.registers.get(&Register::CX) == 0,
+        };
+
+        if should_jump {
+            self.jump_to(new_offset);
+        }
+    }
+
+    /// Resolves to a 20-bit physical address, offsets wrap around within their segment.
+    fn resolve_eac(&mut self, eac: &EffectiveAddressCalc) -> u32 {
+        let offset = self.effective_address(eac);
+
+        let segment = self
+            .segment_override
+            .unwrap_or_else(|| eac.default_segment());
+
+        physical_address(self.registers.get(&segment), offset)
+    }
+
+    /// The offset within the segment, which is all lea is after.
+    fn effective_address(&self, eac: &EffectiveAddressCalc) -> u16 {
+        match eac {
+            EffectiveAddressCalc::SingleReg(reg) => self.registers.get(reg),
             EffectiveAddressCalc::SingleRegPlus(reg, disp) => {
-                let addr_base: u16 = self.registers.get_reg(reg).into();
+                let addr_base = self.registers.get(reg);
 
-                (addr_base as i16 + disp) as u16
+                addr_base.wrapping_add(*disp as u16)
             }
             EffectiveAddressCalc::Plus(reg, reg1) => {
-                let x: u16 = self.registers.get_reg(reg).into();
-                let y: u16 = self.registers.get_reg(reg1).into();
+                let x = self.registers.get(reg);
+                let y = self.registers.get(reg1);
 
-                x + y
+                x.wrapping_add(y)
             }
             EffectiveAddressCalc::PlusConstant(reg, reg1, disp) => {
-                let x: u16 = self.registers.get_reg(reg).into();
-                let y: u16 = self.registers.get_reg(reg1).into();
+                let x = self.registers.get(reg);
+                let y = self.registers.get(reg1);
 
-                ((x + y) as i16 + *disp) as u16
+                x.wrapping_add(y).wrapping_add(*disp as u16)
             }
             EffectiveAddressCalc::DirectAddress(addr) => *addr,
-        };
-
-        addr
+        }
     }
 }
 
@@ -202,6 +985,10 @@ pub struct Registers {
     bp: Word,
     si: Word,
     di: Word,
+    es: Word,
+    cs: Word,
+    ss: Word,
+    ds: Word,
     flags: Flags,
     ip: Word,
 }
@@ -211,39 +998,43 @@ impl Registers {
         Registers::default()
     }
 
-    pub fn get_reg(&mut self, reg: &Register) -> &mut Word {
-        let r: &mut _ = match reg {
-            Register::AL => &mut self.ax,
-            Register::BL => &mut self.bx,
-            Register::CL => &mut self.cx,
-            Register::DL => &mut self.dx,
-            Register::AH => &mut self.ax,
-            Register::BH => &mut self.bx,
-            Register::CH => &mut self.cx,
-            Register::DH => &mut self.dx,
-            Register::AX => &mut self.ax,
-            Register::BX => &mut self.bx,
-            Register::CX => &mut self.cx,
-            Register::DX => &mut self.dx,
-            Register::SI => &mut self.si,
-            Register::DI => &mut self.di,
-            Register::SP => &mut self.sp,
-            Register::BP => &mut self.bp,
-        };
-
-        r
+    /// Reads a register, with the byte registers zero extended to 16 bits.
+    pub fn get(&self, reg: &Register) -> u16 {
+        match reg {
+            Register::AL => self.ax.low as u16,
+            Register::BL => self.bx.low as u16,
+            Register::CL => self.cx.low as u16,
+            Register::DL => self.dx.low as u16,
+            Register::AH => self.ax.high as u16,
+            Register::BH => self.bx.high as u16,
+            Register::CH => self.cx.high as u16,
+            Register::DH => self.dx.high as u16,
+            Register::AX => self.ax.into(),
+            Register::BX => self.bx.into(),
+            Register::CX => self.cx.into(),
+            Register::DX => self.dx.into(),
+            Register::SI => self.si.into(),
+            Register::DI => self.di.into(),
+            Register::SP => self.sp.into(),
+            Register::BP => self.bp.into(),
+            Register::ES => self.es.into(),
+            Register::CS => self.cs.into(),
+            Register::SS => self.ss.into(),
+            Register::DS => self.ds.into(),
+        }
     }
 
+    /// Writes a register, byte registers only take the low 8 bits of `value`.
     pub fn set(&mut self, reg: &Register, value: u16) {
         match reg {
-            Register::AL => self.ax.high = value as u8,
-            Register::BL => self.bx.high = value as u8,
-            Register::CL => self.cx.high = value as u8,
-            Register::DL => self.dx.high = value as u8,
-            Register::AH => self.ax.low = value as u8,
-            Register::BH => self.bx.low = value as u8,
-            Register::CH => self.cx.low = value as u8,
-            Register::DH => self.dx.low = value as u8,
+            Register::AL => self.ax.low = value as u8,
+            Register::BL => self.bx.low = value as u8,
+            Register::CL => self.cx.low = value as u8,
+            Register::DL => self.dx.low = value as u8,
+            Register::AH => self.ax.high = value as u8,
+            Register::BH => self.bx.high = value as u8,
+            Register::CH => self.cx.high = value as u8,
+            Register::DH => self.dx.high = value as u8,
             Register::AX => self.ax = value.into(),
             Register::BX => self.bx = value.into(),
             Register::CX => self.cx = value.into(),
@@ -252,6 +1043,10 @@ impl Registers {
             Register::DI => self.di = value.into(),
             Register::SP => self.sp = value.into(),
             Register::BP => self.bp = value.into(),
+            Register::ES => self.es = value.into(),
+            Register::CS => self.cs = value.into(),
+            Register::SS => self.ss = value.into(),
+            Register::DS => self.ds = value.into(),
         }
     }
 }
@@ -275,6 +1070,10 @@ impl Debug for Registers {
         disp!(bp);
         disp!(si);
         disp!(di);
+        disp!(es);
+        disp!(cs);
+        disp!(ss);
+        disp!(ds);
         disp!(ip);
         disp!(flags, "");
 
@@ -282,10 +1081,140 @@ impl Debug for Registers {
     }
 }
 
+/// Interrupt type raised by div and idiv when the quotient doesn't fit or the divisor is zero.
+const DIVIDE_ERROR: u8 = 0;
+
+/// Interrupt type of int3, the one byte instruction debuggers patch in as a breakpoint.
+const BREAKPOINT: u8 = 3;
+
+/// Interrupt type raised by into when the overflow flag is set.
+const OVERFLOW_TRAP: u8 = 4;
+
+/// Segments start on 16 byte boundaries, and addresses past the first megabyte wrap around.
+fn physical_address(segment: u16, offset: u16) -> u32 {
+    (((segment as u32) << 4) + offset as u32) & mem::ADDRESS_MASK
+}
+
+/// The parity flag only ever looks at the low 8 bits of a result.
+fn has_even_parity(result: u16) -> bool {
+    (result as u8).count_ones().is_multiple_of(2)
+}
+
 #[derive(Default, Clone, Copy, PartialEq)]
 struct Flags {
-    sign: bool,
+    carry: bool,
+    parity: bool,
+    auxiliary_carry: bool,
     zero: bool,
+    sign: bool,
+    trap: bool,
+    interrupt: bool,
+    direction: bool,
+    overflow: bool,
+}
+
+impl Flags {
+    /// Computes `a + b + carry_in`, updating every flag an add affects.
+    fn add(&mut self, a: u16, b: u16, carry_in: u16, wide: bool) -> u16 {
+        let (mask, sign_bit) = width_masks(wide);
+        let (a, b) = (a & mask, b & mask);
+        let full = a as u32 + b as u32 + carry_in as u32;
+        let result = full as u16 & mask;
+
+        self.carry = full > mask as u32;
+        self.auxiliary_carry = (a ^ b ^ result) & 0x10 != 0;
+        self.overflow = (a ^ result) & (b ^ result) & sign_bit != 0;
+        self.set_result(result, wide);
+
+        result
+    }
+
+    /// Computes `a - b - borrow_in`, updating every flag a sub affects.
+    fn sub(&mut self, a: u16, b: u16, borrow_in: u16, wide: bool) -> u16 {
+        let (mask, sign_bit) = width_masks(wide);
+        let (a, b) = (a & mask, b & mask);
+        let result = a.wrapping_sub(b).wrapping_sub(borrow_in) & mask;
+
+        self.carry = (b as u32 + borrow_in as u32) > a as u32;
+        self.auxiliary_carry = (a ^ b ^ result) & 0x10 != 0;
+        self.overflow = (a ^ b) & (a ^ result) & sign_bit != 0;
+        self.set_result(result, wide);
+
+        result
+    }
+
+    /// Sets the flags that only depend on the result: sign, zero and parity.
+    fn set_result(&mut self, result: u16, wide: bool) {
+        let (mask, sign_bit) = width_masks(wide);
+
+        self.sign = result & sign_bit != 0;
+        self.zero = result & mask == 0;
+        self.parity = has_even_parity(result);
+    }
+}
+
+fn width_masks(wide: bool) -> (u16, u16) {
+    if wide {
+        (0xffff, 0x8000)
+    } else {
+        (0xff, 0x80)
+    }
+}
+
+// bit positions in the packed FLAGS register
+const CARRY: u16 = 0;
+const PARITY: u16 = 2;
+const AUXILIARY_CARRY: u16 = 4;
+const ZERO: u16 = 6;
+const SIGN: u16 = 7;
+const TRAP: u16 = 8;
+const INTERRUPT: u16 = 9;
+const DIRECTION: u16 = 10;
+const OVERFLOW: u16 = 11;
+
+/// Bit 1 and the top four bits always read as set on the 8086.
+const RESERVED_BITS: u16 = 0xf002;
+
+impl From<Flags> for u16 {
+    fn from(flags: Flags) -> Self {
+        let mut packed = RESERVED_BITS;
+
+        let mut set = |bit: u16, is_set| {
+            if is_set {
+                packed |= 1 << bit;
+            }
+        };
+
+        set(CARRY, flags.carry);
+        set(PARITY, flags.parity);
+        set(AUXILIARY_CARRY, flags.auxiliary_carry);
+        set(ZERO, flags.zero);
+        set(SIGN, flags.sign);
+        set(TRAP, flags.trap);
+        set(INTERRUPT, flags.interrupt);
+        set(DIRECTION, flags.direction);
+        set(OVERFLOW, flags.overflow);
+
+        packed
+    }
+}
+
+impl From<u16> for Flags {
+    fn from(packed: u16) -> Self {
+        let is_set = |bit: u16| packed & (1 << bit) != 0;
+
+        Self {
+            carry: is_set(CARRY),
+            parity: is_set(PARITY),
+            auxiliary_carry: is_set(AUXILIARY_CARRY),
+            zero: is_set(ZERO),
+            sign: is_set(SIGN),
+            trap: is_set(TRAP),
+            interrupt: is_set(INTERRUPT),
+            direction: is_set(DIRECTION),
+            overflow: is_set(OVERFLOW),
+        }
+    }
 }
 
 impl Debug for Flags {
@@ -297,8 +1226,15 @@ impl Debug for Flags {
             Ok(())
         };
 
+        display("C", self.carry)?;
+        display("P", self.parity)?;
+        display("A", self.auxiliary_carry)?;
+        display("Z", self.zero)?;
         display("S", self.sign)?;
-        display("Z", self.zero)
+        display("T", self.trap)?;
+        display("I", self.interrupt)?;
+        display("D", self.direction)?;
+        display("O", self.overflow)
     }
 }
 
@@ -332,6 +1268,10 @@ impl Debug for RegistersDiff {
         disp!(bp);
         disp!(si);
         disp!(di);
+        disp!(es);
+        disp!(cs);
+        disp!(ss);
+        disp!(ds);
         disp!(ip);
         disp!(flags, "");
 
@@ -342,30 +1282,48 @@ impl Debug for RegistersDiff {
 mod mem {
     use crate::Word;
 
-    const MEMORY_SIZE: usize = 65536;
+    /// The 8086 has 20 address lines, for a megabyte of physical memory.
+    pub const MEMORY_SIZE: usize = 1 << 20;
+    pub const ADDRESS_MASK: u32 = MEMORY_SIZE as u32 - 1;
 
     pub struct Memory {
-        buffer: [u8; MEMORY_SIZE],
+        buffer: Vec<u8>,
     }
 
     impl Memory {
         pub fn new() -> Self {
             Self {
-                buffer: [0; MEMORY_SIZE],
+                buffer: vec![0; MEMORY_SIZE],
             }
         }
 
-        pub fn store(&mut self, addr: u16, word: impl Into<Word>) {
-            let addr = addr as usize;
+        pub fn store(&mut self, addr: u32, word: impl Into<Word>) {
             let word: Word = word.into();
 
-            self.buffer[addr] = word.low;
-            self.buffer[addr + 1] = word.high;
+            self.store_byte(addr, word.low);
+            self.store_byte(addr + 1, word.high);
+        }
+
+        pub fn load(&mut self, addr: u32) -> Word {
+            Word::new(self.load_byte(addr + 1), self.load_byte(addr))
+        }
+
+        pub fn store_byte(&mut self, addr: u32, byte: u8) {
+            self.buffer[(addr & ADDRESS_MASK) as usize] = byte;
         }
 
-        pub fn load(&mut self, addr: u16) -> Word {
-            let addr = addr as usize;
-            Word::new(self.buffer[addr + 1], self.buffer[addr])
+        pub fn load_byte(&mut self, addr: u32) -> u8 {
+            self.buffer[(addr & ADDRESS_MASK) as usize]
+        }
+
+        pub fn store_bytes(&mut self, addr: u32, bytes: &[u8]) {
+            let start = addr as usize;
+            self.buffer[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+
+        /// Everything from `addr` up to the end of memory.
+        pub fn bytes_from(&self, addr: u32) -> &[u8] {
+            &self.buffer[addr as usize..]
         }
 
         pub fn dump(&self) -> Vec<u8> {
@@ -374,8 +1332,39 @@ mod mem {
     }
 }
 
+/// The 8086 has a separate 64K space of I/O ports next to memory, reached only through
+/// in and out. Devices are modelled by implementing [`io::IoBus`] and handing it to
+/// [`Executor::with_io_bus`].
+pub mod io {
+    /// Answers reads from and writes to I/O ports. Byte accesses only look at and return
+    /// the low byte, word accesses cover `port` and `port + 1`.
+    pub trait IoBus {
+        fn read(&mut self, port: u16, wide: bool) -> u16;
+        fn write(&mut self, port: u16, value: u16, wide: bool);
+    }
+
+    /// A bus with nothing attached, which prints every access to stderr. Reads see the data
+    /// lines floating high.
+    pub struct LoggingBus;
+
+    impl IoBus for LoggingBus {
+        fn read(&mut self, port: u16, wide: bool) -> u16 {
+            let value = if wide { 0xffff } else { 0xff };
+            eprintln!("in port {:#x} -> {:#x}", port, value);
+            value
+        }
+
+        fn write(&mut self, port: u16, value: u16, _wide: bool) {
+            eprintln!("out port {:#x} <- {:#x}", port, value);
+        }
+    }
+}
+
 pub mod clock_est {
-    use crate::{mov, Instruction, Opcode, Operand, Register};
+    use crate::{
+        call::Call, inc::Inc, input::In, jmp::Jmp, jump::J, mov::Mov, output::Out, pop::Pop,
+        push::Push, ret::Ret, xchg::Xchg, Instruction, Opcode, Operand, Register,
+    };
 
     #[derive(Debug)]
     pub struct ClockEstimate {
@@ -389,61 +1378,174 @@ pub mod clock_est {
         }
     }
 
+    /// Clocks from the 8086 manual's instruction timings. Where the time depends on the data,
+    /// the estimate is the least the instruction can take: the fastest multiply or divide, a
+    /// jump not taken, no bits shifted by cl and no repetitions of a string instruction.
     impl From<Instruction> for ClockEstimate {
         fn from(value: Instruction) -> Self {
-            use Operand as O;
-
-            let mut ea = None;
-
-            let base = match value.opcode {
-                Opcode::Mov(m) => match m {
-                    mov::Mov::RM => match (value.destination, value.source.unwrap()) {
-                        (O::Register(_), O::Register(_)) => 2,
-                        (O::Register(_), O::MemoryAddress(eac)) => {
-                            ea = Some(ea_clock(&eac));
-                            8
-                        }
-                        (O::MemoryAddress(eac), O::Register(_)) => {
-                            ea = Some(ea_clock(&eac));
-                            9
-                        }
-                        operands => todo!("{:?}", operands),
-                    },
-                    mov::Mov::ImmToReg => 4,
-                    mov::Mov::ImmToRegOrMem => todo!(),
-                    mov::Mov::MemToAcc => 10,
-                    mov::Mov::AccToMem => 10,
+            let mut ea = [&value.destination, &value.source]
+                .into_iter()
+                .find_map(|operand| match operand {
+                    Some(Operand::MemoryAddress(eac)) => Some(ea_clock(eac)),
+                    _ => None,
+                });
+            let memory = ea.is_some();
+
+            let base = match &value.opcode {
+                Opcode::Mov(Mov::MemToAcc | Mov::AccToMem) => {
+                    // the address is part of the instruction, there's nothing to calculate
+                    ea = None;
+                    10
+                }
+                Opcode::Mov(_) => two_operands(&value, [2, 8, 9, 4, 10]),
+                Opcode::Add(crate::add::Add::ImmToAcc)
+                | Opcode::Adc(crate::adc::Adc::ImmToAcc)
+                | Opcode::Sub(crate::sub::Sub::ImmToAcc)
+                | Opcode::Sbb(crate::sbb::Sbb::ImmToAcc)
+                | Opcode::Cmp(crate::cmp::Cmp::ImmToAcc)
+                | Opcode::And(crate::and::And::ImmToAcc)
+                | Opcode::Or(crate::or::Or::ImmToAcc)
+                | Opcode::Xor(crate::xor::Xor::ImmToAcc)
+                | Opcode::Test(crate::test::Test::ImmToAcc) => 4,
+                Opcode::Add(_)
+                | Opcode::Adc(_)
+                | Opcode::Sub(_)
+                | Opcode::Sbb(_)
+                | Opcode::And(_)
+                | Opcode::Or(_)
+                | Opcode::Xor(_) => two_operands(&value, [3, 9, 16, 4, 17]),
+                Opcode::Cmp(_) => two_operands(&value, [3, 9, 9, 4, 10]),
+                Opcode::Test(_) => two_operands(&value, [3, 9, 9, 5, 11]),
+                Opcode::Not | Opcode::Neg => register_or_memory(memory, 3, 16),
+                Opcode::Mul => multiply(&value, memory, [70, 118, 76, 124]),
+                Opcode::Imul => multiply(&value, memory, [80, 128, 86, 134]),
+                Opcode::Div => multiply(&value, memory, [80, 144, 86, 150]),
+                Opcode::Idiv => multiply(&value, memory, [101, 165, 107, 171]),
+                Opcode::Inc(Inc::Reg) | Opcode::Dec(crate::dec::Dec::Reg) => 2,
+                Opcode::Inc(_) | Opcode::Dec(_) => match (memory, value.wide) {
+                    (true, _) => 15,
+                    (false, true) => 2,
+                    (false, false) => 3,
                 },
-                Opcode::Add(a) => match a {
-                    crate::add::Add::RM => match (value.destination, value.source.unwrap()) {
-                        (O::Register(_), O::Register(_)) => 3,
-                        (O::Register(_), O::MemoryAddress(eac)) => {
-                            ea = Some(ea_clock(&eac));
-                            9
-                        }
-                        (O::MemoryAddress(eac), O::Register(_)) => {
-                            ea = Some(ea_clock(&eac));
-                            16
-                        }
-                        operands => todo!("{:?}", operands),
-                    },
-                    crate::add::Add::ImmToRegOrMem => {
-                        match (value.destination, value.source.unwrap()) {
-                            (O::Register(_), O::WordImmediate(_)) => 4,
-                            operands => todo!("{:?}", operands),
-                        }
+                Opcode::Shift(_) => {
+                    let by_cl = matches!(value.source, Some(Operand::Register(Register::CL)));
+                    match (memory, by_cl) {
+                        (false, false) => 2,
+                        (false, true) => 8,
+                        (true, false) => 15,
+                        (true, true) => 20,
                     }
-                    crate::add::Add::ImmToAcc => todo!(),
+                }
+                Opcode::Call(c) => match c {
+                    Call::Direct => 19,
+                    Call::DirectFar => 28,
+                    Call::Indirect => register_or_memory(memory, 16, 21),
+                    Call::IndirectFar => 37,
+                },
+                Opcode::Jmp(j) => match j {
+                    Jmp::Short | Jmp::Direct | Jmp::DirectFar => 15,
+                    Jmp::Indirect => register_or_memory(memory, 11, 18),
+                    Jmp::IndirectFar => 24,
+                },
+                Opcode::Ret(r) => match (r, value.destination.is_some()) {
+                    (Ret::Near, false) => 8,
+                    (Ret::Near, true) => 12,
+                    (Ret::Far, false) => 18,
+                    (Ret::Far, true) => 17,
+                },
+                Opcode::Push(p) => match p {
+                    Push::Seg => 10,
+                    Push::Reg | Push::RegOrMem => register_or_memory(memory, 11, 16),
+                },
+                Opcode::Pop(p) => match p {
+                    Pop::Seg => 8,
+                    Pop::Reg | Pop::RegOrMem => register_or_memory(memory, 8, 17),
+                },
+                Opcode::Pushf => 10,
+                Opcode::Popf => 8,
+                // every repetition adds to this, and there may not be any
+                Opcode::Movs | Opcode::Cmps | Opcode::Scas | Opcode::Lods | Opcode::Stos
+                    if value.repeat.is_some() =>
+                {
+                    9
+                }
+                Opcode::Movs => 18,
+                Opcode::Cmps => 22,
+                Opcode::Scas => 15,
+                Opcode::Lods => 12,
+                Opcode::Stos => 11,
+                Opcode::Int => 51,
+                Opcode::Int3 => 52,
+                Opcode::Into => 4,
+                Opcode::Iret => 24,
+                Opcode::Xchg(Xchg::RegWithAcc) => 3,
+                Opcode::Xchg(Xchg::RM) => register_or_memory(memory, 4, 17),
+                Opcode::Xlat => 11,
+                Opcode::Lea => 2,
+                Opcode::Lds | Opcode::Les => 16,
+                Opcode::Lahf | Opcode::Sahf => 4,
+                Opcode::Cbw => 2,
+                Opcode::Cwd => 5,
+                Opcode::Daa | Opcode::Das | Opcode::Aaa | Opcode::Aas => 4,
+                Opcode::Aam => 83,
+                Opcode::Aad => 60,
+                Opcode::Clc
+                | Opcode::Stc
+                | Opcode::Cmc
+                | Opcode::Cld
+                | Opcode::Std
+                | Opcode::Cli
+                | Opcode::Sti
+                | Opcode::Hlt => 2,
+                Opcode::Wait => 3,
+                Opcode::Esc => register_or_memory(memory, 2, 8),
+                Opcode::Nop => 3,
+                Opcode::In(In::FixedPort) | Opcode::Out(Out::FixedPort) => 10,
+                Opcode::In(In::VariablePort) | Opcode::Out(Out::VariablePort) => 8,
+                Opcode::J(j) => match j {
+                    J::Loop | J::Loopnz => 5,
+                    J::Loopz | J::Jcxz => 6,
+                    _ => 4,
                 },
-                Opcode::Sub(_) => todo!(),
-                Opcode::Cmp(_) => todo!(),
-                Opcode::J(_) => todo!(),
             };
 
-            Self { base, ea }
+            let lock = if value.lock { 2 } else { 0 };
+
+            Self {
+                base: base + lock,
+                ea,
+            }
+        }
+    }
+
+    /// Picks from the clocks the manual gives for reg,reg reg,mem mem,reg reg,imm and mem,imm,
+    /// in that order.
+    fn two_operands(i: &Instruction, clocks: [usize; 5]) -> usize {
+        use Operand as O;
+
+        match (&i.destination, &i.source) {
+            (Some(O::MemoryAddress(_)), Some(O::Register(_))) => clocks[2],
+            (Some(O::MemoryAddress(_)), _) => clocks[4],
+            (_, Some(O::MemoryAddress(_))) => clocks[1],
+            (_, Some(O::Register(_))) => clocks[0],
+            _ => clocks[3],
         }
     }
 
+    fn register_or_memory(memory: bool, register_clocks: usize, memory_clocks: usize) -> usize {
+        if memory {
+            memory_clocks
+        } else {
+            register_clocks
+        }
+    }
+
+    /// Picks from the clocks for a byte register, a word register, a byte in memory and a word
+    /// in memory, in that order.
+    fn multiply(i: &Instruction, memory: bool, clocks: [usize; 4]) -> usize {
+        clocks[(memory as usize) << 1 | i.wide as usize]
+    }
+
     fn ea_clock(eac: &crate::EffectiveAddressCalc) -> usize {
         match eac {
             crate::EffectiveAddressCalc::SingleReg(_) => 5,
@@ -1,30 +1,200 @@
 use std::fmt::{Debug, Display};
 
+use adc::Adc;
 use add::Add;
+use and::And;
+use call::Call;
 use cmp::Cmp;
+use dec::Dec;
+use inc::Inc;
+use input::In;
+use jmp::Jmp;
 use mov::Mov;
+use or::Or;
+use output::Out;
+use pop::Pop;
+use push::Push;
+use ret::Ret;
+use sbb::Sbb;
+use shift::Shift;
 use sub::Sub;
+use test::Test;
+use xchg::Xchg;
+use xor::Xor;
 
 use crate::jump::J;
 
+pub mod asm;
 pub mod decode;
+pub mod encode;
 pub mod exec;
+pub mod listing;
 
 pub struct Instruction {
     opcode: Opcode,
+    /// Whether the instruction operates on words (W=1) or bytes (W=0).
+    wide: bool,
+    /// Segment register named by a segment override prefix, if there was one.
+    segment_override: Option<Register>,
+    repeat: Option<Repeat>,
+    /// Whether a lock prefix asked for the bus to be held for the whole instruction.
+    lock: bool,
+    /// The D bit the instruction was decoded with. Between two registers either setting
+    /// works, and this keeps [`encode::encode`] picking the same one.
+    reg_is_destination: Option<bool>,
+    /// The S bit the instruction was decoded with, since a word immediate that fits in a byte
+    /// can be encoded either way.
+    sign_extend: Option<bool>,
     source: Option<Operand>,
-    destination: Operand,
+    /// None for instructions without any operands, like pushf.
+    destination: Option<Operand>,
+}
+
+impl Instruction {
+    /// Starts an instruction with no operands or prefixes, operating on words if `wide`.
+    ///
+    /// Operands and prefixes are added with the `with_` methods, so that a program can be put
+    /// together in Rust and handed to [`encode::encode`].
+    pub fn new(opcode: Opcode, wide: bool) -> Self {
+        Instruction {
+            opcode,
+            wide,
+            segment_override: None,
+            repeat: None,
+            lock: false,
+            reg_is_destination: None,
+            sign_extend: None,
+            source: None,
+            destination: None,
+        }
+    }
+
+    pub fn with_destination(mut self, destination: Operand) -> Self {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn with_source(mut self, source: Operand) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_segment_override(mut self, segment: Register) -> Self {
+        self.segment_override = Some(segment);
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = Some(repeat);
+        self
+    }
+
+    pub fn with_lock(mut self) -> Self {
+        self.lock = true;
+        self
+    }
+
+    fn destination(&self) -> &Operand {
+        self.destination
+            .as_ref()
+            .unwrap_or_else(|| panic!("{:?} to have a destination", self.opcode))
+    }
+
+    /// NASM can't tell the size of a memory operand unless another operand gives it away.
+    fn needs_size_qualifier(&self) -> bool {
+        use Operand as O;
+
+        match (&self.destination, &self.source) {
+            // branch targets are always words, or far pointers
+            (Some(O::MemoryAddress(_)), None) => {
+                !matches!(self.opcode, Opcode::Call(_) | Opcode::Jmp(_))
+            }
+            (Some(O::MemoryAddress(_)), Some(O::Immediate(_))) => true,
+            // the count in cl says nothing about the size of what's being shifted
+            (Some(O::MemoryAddress(_)), Some(O::Register(_))) => {
+                matches!(self.opcode, Opcode::Shift(_))
+            }
+            _ => false,
+        }
+    }
+
+    fn fmt_operand(&self, operand: &Operand) -> String {
+        match (operand, &self.segment_override) {
+            (Operand::MemoryAddress(eac), Some(segment)) => {
+                format!("[{}:{}]", segment, eac.address_expression())
+            }
+            _ => operand.to_string(),
+        }
+    }
 }
 
 impl Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.lock {
+            write!(f, "lock ")?;
+        }
+
+        if let Some(repeat) = &self.repeat {
+            let prefix = match (repeat, &self.opcode) {
+                (Repeat::Rep, Opcode::Cmps | Opcode::Scas) => "repe",
+                (Repeat::Rep, _) => "rep",
+                (Repeat::Repne, _) => "repne",
+            };
+            write!(f, "{} ", prefix)?;
+        }
+
+        // string instructions and xlat have no memory operand to show the override on
+        if let Some(segment) = &self.segment_override {
+            let operands = [&self.destination, &self.source];
+            if !operands
+                .iter()
+                .any(|operand| matches!(operand, Some(Operand::MemoryAddress(_))))
+            {
+                write!(f, "{} ", segment)?;
+            }
+        }
+
+        write!(f, "{}", self.opcode)?;
+
+        if self.opcode.is_string() {
+            write!(f, "{}", if self.wide { "w" } else { "b" })?;
+        }
+
+        let Some(destination) = &self.destination else {
+            return Ok(());
+        };
+
+        if self.needs_size_qualifier() {
+            write!(f, " {}", if self.wide { "word" } else { "byte" })?;
+        }
+
+        // NASM picks the shortest jmp that reaches a label, which isn't always the one decoded
+        if let (Opcode::Jmp(j), Operand::Label(_)) = (&self.opcode, destination) {
+            match j {
+                Jmp::Short => write!(f, " short")?,
+                Jmp::Direct => write!(f, " near")?,
+                _ => {}
+            }
+        }
+
+        let destination = self.fmt_operand(destination);
+
         match &self.source {
-            Some(source) => write!(f, "{} {}, {}", self.opcode, self.destination, source),
-            None => write!(f, "{} {}", self.opcode, self.destination),
+            Some(source) => write!(f, " {}, {}", destination, self.fmt_operand(source)),
+            None => write!(f, " {}", destination),
         }
     }
 }
 
+/// The F3 and F2 prefixes, which repeat a string instruction until CX runs out.
+#[derive(Debug, Clone, Copy)]
+pub enum Repeat {
+    /// rep, or repe/repz for cmps and scas, which also stop once ZF is clear.
+    Rep,
+    /// repne/repnz, only for cmps and scas, which stop once ZF is set.
+    Repne,
+}
+
 #[derive(Debug)]
 pub enum Operand {
     Register(Register),
@@ -33,6 +203,11 @@ pub enum Operand {
     ByteImmediate(u8),
     WordImmediate(u16),
     InstPtrIncrement(i8),
+    NearInstPtrIncrement(i16),
+    /// A jump target given a name by [`listing::Listing::with_labels`], printed as label_N.
+    Label(usize),
+    /// segment:offset
+    FarPointer(u16, u16),
 }
 
 #[derive(Debug)]
@@ -45,19 +220,23 @@ pub enum EffectiveAddressCalc {
 }
 
 impl EffectiveAddressCalc {
-    fn with_no_disp<F: FnMut() -> u16>(rm: u8, mut da_value: F) -> Self {
+    /// `da_value` reads the direct address that follows when R/M is 110, which can fail if
+    /// the input runs out.
+    fn with_no_disp<E, F: FnMut() -> Result<u16, E>>(rm: u8, mut da_value: F) -> Result<Self, E> {
         use Register as R;
-        match rm {
+        let eac = match rm {
             0 => Self::Plus(R::BX, R::SI),
             1 => Self::Plus(R::BX, R::DI),
             2 => Self::Plus(R::BP, R::SI),
             3 => Self::Plus(R::BP, R::DI),
             4 => Self::SingleReg(R::SI),
             5 => Self::SingleReg(R::DI),
-            6 => Self::DirectAddress(da_value()),
+            6 => Self::DirectAddress(da_value()?),
             7 => Self::SingleReg(R::BX),
             _ => unreachable!(),
-        }
+        };
+
+        Ok(eac)
     }
 
     fn with_disp(rm: u8, disp: i16) -> Self {
@@ -74,11 +253,21 @@ impl EffectiveAddressCalc {
             _ => unreachable!(),
         }
     }
-}
 
-impl Display for EffectiveAddressCalc {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let a = match self {
+    /// BP based addresses are relative to the stack segment, everything else to the data segment.
+    fn default_segment(&self) -> Register {
+        use Register as R;
+        match self {
+            Self::SingleRegPlus(R::BP, _)
+            | Self::Plus(R::BP, _)
+            | Self::PlusConstant(R::BP, _, _) => R::SS,
+            _ => R::DS,
+        }
+    }
+
+    /// What goes between the brackets.
+    fn address_expression(&self) -> String {
+        match self {
             EffectiveAddressCalc::SingleReg(r) => r.to_string(),
             EffectiveAddressCalc::SingleRegPlus(r, c) => {
                 if c.signum() == -1 {
@@ -96,9 +285,13 @@ impl Display for EffectiveAddressCalc {
                 }
             }
             EffectiveAddressCalc::DirectAddress(c) => c.to_string(),
-        };
+        }
+    }
+}
 
-        write!(f, "[{}]", a)
+impl Display for EffectiveAddressCalc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}]", self.address_expression())
     }
 }
 impl Display for Operand {
@@ -112,20 +305,27 @@ impl Display for Operand {
                 Operand::Immediate(value) => value.to_string(),
                 Operand::ByteImmediate(b) => format!("byte {}", b),
                 Operand::WordImmediate(w) => format!("word {}", w),
-                Operand::InstPtrIncrement(p) => format!(
-                    "${}",
-                    if p.signum() >= 0 {
-                        format!("+{}", p)
-                    } else {
-                        format!("{}", p)
-                    }
-                ),
+                Operand::InstPtrIncrement(p) => format_inst_ptr_increment(*p as i16),
+                Operand::NearInstPtrIncrement(p) => format_inst_ptr_increment(*p),
+                Operand::Label(n) => format!("label_{}", n),
+                Operand::FarPointer(segment, offset) => format!("{}:{}", segment, offset),
             }
         )
     }
 }
 
-#[derive(Debug)]
+fn format_inst_ptr_increment(p: i16) -> String {
+    format!(
+        "${}",
+        if p.signum() >= 0 {
+            format!("+{}", p)
+        } else {
+            format!("{}", p)
+        }
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Register {
     // low
     AL,
@@ -147,6 +347,11 @@ pub enum Register {
     DI,
     SP,
     BP,
+    // segment
+    ES,
+    CS,
+    SS,
+    DS,
 }
 
 impl Display for Register {
@@ -174,11 +379,21 @@ impl Register {
             (0b110, 1) => Register::SI,
             (0b111, 0) => Register::BH,
             (0b111, 1) => Register::DI,
-            _ => todo!(),
+            _ => return None,
         };
 
         Some(r)
     }
+
+    fn try_from_segment(code: &u8) -> Option<Self> {
+        match code {
+            0b00 => Some(Register::ES),
+            0b01 => Some(Register::CS),
+            0b10 => Some(Register::SS),
+            0b11 => Some(Register::DS),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Default, PartialEq)]
@@ -191,11 +406,6 @@ impl Word {
     fn new(high: u8, low: u8) -> Self {
         Self { high, low }
     }
-
-    fn little_endian(mut self) -> Self {
-        std::mem::swap(&mut self.high, &mut self.low);
-        self
-    }
 }
 
 impl From<&mut Word> for u16 {
@@ -246,11 +456,70 @@ impl Debug for Word {
 }
 
 #[derive(Debug)]
-enum Opcode {
+pub enum Opcode {
     Mov(mov::Mov),
     Add(add::Add),
+    Adc(adc::Adc),
     Sub(sub::Sub),
+    Sbb(sbb::Sbb),
     Cmp(cmp::Cmp),
+    And(and::And),
+    Or(or::Or),
+    Xor(xor::Xor),
+    Test(test::Test),
+    Not,
+    Neg,
+    Mul,
+    Imul,
+    Div,
+    Idiv,
+    Inc(inc::Inc),
+    Dec(dec::Dec),
+    Shift(shift::Shift),
+    Call(call::Call),
+    Jmp(jmp::Jmp),
+    Push(push::Push),
+    Pop(pop::Pop),
+    Pushf,
+    Popf,
+    Ret(ret::Ret),
+    Movs,
+    Cmps,
+    Scas,
+    Lods,
+    Stos,
+    Int,
+    Int3,
+    Into,
+    Iret,
+    Xchg(xchg::Xchg),
+    Xlat,
+    Lea,
+    Lds,
+    Les,
+    Lahf,
+    Sahf,
+    Cbw,
+    Cwd,
+    Daa,
+    Das,
+    Aaa,
+    Aas,
+    Aam,
+    Aad,
+    Clc,
+    Stc,
+    Cmc,
+    Cld,
+    Std,
+    Cli,
+    Sti,
+    Hlt,
+    Wait,
+    Esc,
+    Nop,
+    In(In),
+    Out(Out),
     J(jump::J),
 }
 
@@ -259,8 +528,70 @@ impl Display for Opcode {
         let s = match self {
             Opcode::Mov(_) => "mov",
             Opcode::Add(_) => "add",
+            Opcode::Adc(_) => "adc",
             Opcode::Sub(_) => "sub",
+            Opcode::Sbb(_) => "sbb",
             Opcode::Cmp(_) => "cmp",
+            Opcode::And(_) => "and",
+            Opcode::Or(_) => "or",
+            Opcode::Xor(_) => "xor",
+            Opcode::Test(_) => "test",
+            Opcode::Not => "not",
+            Opcode::Neg => "neg",
+            Opcode::Mul => "mul",
+            Opcode::Imul => "imul",
+            Opcode::Div => "div",
+            Opcode::Idiv => "idiv",
+            Opcode::Inc(_) => "inc",
+            Opcode::Dec(_) => "dec",
+            Opcode::Shift(s) => return write!(f, "{}", format!("{:?}", s).to_lowercase()),
+            Opcode::Call(Call::IndirectFar) => "call far",
+            Opcode::Call(_) => "call",
+            Opcode::Jmp(Jmp::IndirectFar) => "jmp far",
+            Opcode::Jmp(_) => "jmp",
+            Opcode::Push(_) => "push",
+            Opcode::Pop(_) => "pop",
+            Opcode::Pushf => "pushf",
+            Opcode::Popf => "popf",
+            Opcode::Ret(Ret::Near) => "ret",
+            Opcode::Ret(Ret::Far) => "retf",
+            Opcode::Movs => "movs",
+            Opcode::Cmps => "cmps",
+            Opcode::Scas => "scas",
+            Opcode::Lods => "lods",
+            Opcode::Stos => "stos",
+            Opcode::Int => "int",
+            Opcode::Int3 => "int3",
+            Opcode::Into => "into",
+            Opcode::Iret => "iret",
+            Opcode::Xchg(_) => "xchg",
+            Opcode::Xlat => "xlat",
+            Opcode::Lea => "lea",
+            Opcode::Lds => "lds",
+            Opcode::Les => "les",
+            Opcode::Lahf => "lahf",
+            Opcode::Sahf => "sahf",
+            Opcode::Cbw => "cbw",
+            Opcode::Cwd => "cwd",
+            Opcode::Daa => "daa",
+            Opcode::Das => "das",
+            Opcode::Aaa => "aaa",
+            Opcode::Aas => "aas",
+            Opcode::Aam => "aam",
+            Opcode::Aad => "aad",
+            Opcode::Clc => "clc",
+            Opcode::Stc => "stc",
+            Opcode::Cmc => "cmc",
+            Opcode::Cld => "cld",
+            Opcode::Std => "std",
+            Opcode::Cli => "cli",
+            Opcode::Sti => "sti",
+            Opcode::Hlt => "hlt",
+            Opcode::Wait => "wait",
+            Opcode::Esc => "esc",
+            Opcode::Nop => "nop",
+            Opcode::In(_) => "in",
+            Opcode::Out(_) => "out",
             Opcode::J(j) => return write!(f, "{}", format!("{:?}", j).to_lowercase()),
         };
         write!(f, "{}", s)
@@ -268,8 +599,14 @@ impl Display for Opcode {
 }
 
 impl Opcode {
+    fn is_string(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Movs | Opcode::Cmps | Opcode::Scas | Opcode::Lods | Opcode::Stos
+        )
+    }
+
     fn try_from(word: &Word) -> Option<Opcode> {
-        // println!("word {:b}", word);
         let first_four_bits = word.high >> 4;
         let first_six_bits = word.high >> 2;
         let first_seven_bits = word.high >> 1;
@@ -277,16 +614,40 @@ impl Opcode {
         match first_six_bits {
             0b100010 => Some(Opcode::Mov(Mov::RM)),
             0b000000 => Some(Opcode::Add(Add::RM)),
+            0b000100 => Some(Opcode::Adc(Adc::RM)),
             0b001010 => Some(Opcode::Sub(Sub::RM)),
+            0b000110 => Some(Opcode::Sbb(Sbb::RM)),
             0b001110 => Some(Opcode::Cmp(Cmp::RM)),
+            0b001000 => Some(Opcode::And(And::RM)),
+            0b000010 => Some(Opcode::Or(Or::RM)),
+            0b001100 => Some(Opcode::Xor(Xor::RM)),
+            0b110100 => {
+                let b = (word.low & 0b00111000) >> 3;
+
+                match b {
+                    0b000 => Some(Opcode::Shift(Shift::Rol)),
+                    0b001 => Some(Opcode::Shift(Shift::Ror)),
+                    0b010 => Some(Opcode::Shift(Shift::Rcl)),
+                    0b011 => Some(Opcode::Shift(Shift::Rcr)),
+                    0b100 => Some(Opcode::Shift(Shift::Shl)), // Sal
+                    0b101 => Some(Opcode::Shift(Shift::Shr)),
+                    0b111 => Some(Opcode::Shift(Shift::Sar)),
+                    _ => None,
+                }
+            }
             0b100000 => {
                 let b = (word.low & 0b00111000) >> 3;
 
                 match b {
                     0b000 => Some(Opcode::Add(Add::ImmToRegOrMem)),
+                    0b001 => Some(Opcode::Or(Or::ImmToRegOrMem)),
+                    0b010 => Some(Opcode::Adc(Adc::ImmToRegOrMem)),
+                    0b011 => Some(Opcode::Sbb(Sbb::ImmToRegOrMem)),
+                    0b100 => Some(Opcode::And(And::ImmToRegOrMem)),
                     0b101 => Some(Opcode::Sub(Sub::ImmToRegOrMem)),
+                    0b110 => Some(Opcode::Xor(Xor::ImmToRegOrMem)),
                     0b111 => Some(Opcode::Cmp(Cmp::ImmToRegOrMem)),
-                    _ => todo!(),
+                    _ => unreachable!(),
                 }
             }
             _ => match first_four_bits {
@@ -296,8 +657,54 @@ impl Opcode {
                     0b1010000 => Some(Opcode::Mov(Mov::MemToAcc)),
                     0b1010001 => Some(Opcode::Mov(Mov::AccToMem)),
                     0b0000010 => Some(Opcode::Add(Add::ImmToAcc)),
+                    0b0001010 => Some(Opcode::Adc(Adc::ImmToAcc)),
                     0b0010110 => Some(Opcode::Sub(Sub::ImmToAcc)),
+                    0b0001110 => Some(Opcode::Sbb(Sbb::ImmToAcc)),
                     0b0011110 => Some(Opcode::Cmp(Cmp::ImmToAcc)),
+                    0b0010010 => Some(Opcode::And(And::ImmToAcc)),
+                    0b0000110 => Some(Opcode::Or(Or::ImmToAcc)),
+                    0b0011010 => Some(Opcode::Xor(Xor::ImmToAcc)),
+                    0b1000010 => Some(Opcode::Test(Test::RM)),
+                    0b1000011 => Some(Opcode::Xchg(Xchg::RM)),
+                    0b1010100 => Some(Opcode::Test(Test::ImmToAcc)),
+                    0b1010010 => Some(Opcode::Movs),
+                    0b1010011 => Some(Opcode::Cmps),
+                    0b1010111 => Some(Opcode::Scas),
+                    0b1010110 => Some(Opcode::Lods),
+                    0b1010101 => Some(Opcode::Stos),
+                    0b1110010 => Some(Opcode::In(In::FixedPort)),
+                    0b1110110 => Some(Opcode::In(In::VariablePort)),
+                    0b1110011 => Some(Opcode::Out(Out::FixedPort)),
+                    0b1110111 => Some(Opcode::Out(Out::VariablePort)),
+                    0b1111011 => {
+                        let b = (word.low & 0b00111000) >> 3;
+
+                        match b {
+                            0b000 => Some(Opcode::Test(Test::ImmToRegOrMem)),
+                            0b010 => Some(Opcode::Not),
+                            0b011 => Some(Opcode::Neg),
+                            0b100 => Some(Opcode::Mul),
+                            0b101 => Some(Opcode::Imul),
+                            0b110 => Some(Opcode::Div),
+                            0b111 => Some(Opcode::Idiv),
+                            _ => None,
+                        }
+                    }
+                    0b1111111 => {
+                        let b = (word.low & 0b00111000) >> 3;
+                        let wide = word.high & 1 == 1;
+
+                        match (b, wide) {
+                            (0b000, _) => Some(Opcode::Inc(Inc::RegOrMem)),
+                            (0b001, _) => Some(Opcode::Dec(Dec::RegOrMem)),
+                            (0b010, true) => Some(Opcode::Call(Call::Indirect)),
+                            (0b011, true) => Some(Opcode::Call(Call::IndirectFar)),
+                            (0b100, true) => Some(Opcode::Jmp(Jmp::Indirect)),
+                            (0b101, true) => Some(Opcode::Jmp(Jmp::IndirectFar)),
+                            (0b110, true) => Some(Opcode::Push(Push::RegOrMem)),
+                            _ => None,
+                        }
+                    }
                     _ => match word.high {
                         0b01110101 => Some(Opcode::J(J::Jne)),
                         0b01110100 => Some(Opcode::J(J::Je)),
@@ -319,7 +726,62 @@ impl Opcode {
                         0b11100001 => Some(Opcode::J(J::Loopz)),
                         0b11100000 => Some(Opcode::J(J::Loopnz)),
                         0b11100011 => Some(Opcode::J(J::Jcxz)),
-                        _ => None,
+                        0b10001110 => Some(Opcode::Mov(Mov::RegOrMemToSeg)),
+                        0b10001100 => Some(Opcode::Mov(Mov::SegToRegOrMem)),
+                        0b10001111 if (word.low & 0b00111000) == 0 => {
+                            Some(Opcode::Pop(Pop::RegOrMem))
+                        }
+                        0b11101000 => Some(Opcode::Call(Call::Direct)),
+                        0b10011010 => Some(Opcode::Call(Call::DirectFar)),
+                        0b11101011 => Some(Opcode::Jmp(Jmp::Short)),
+                        0b11101001 => Some(Opcode::Jmp(Jmp::Direct)),
+                        0b11101010 => Some(Opcode::Jmp(Jmp::DirectFar)),
+                        0b11000011 | 0b11000010 => Some(Opcode::Ret(Ret::Near)),
+                        0b11001011 | 0b11001010 => Some(Opcode::Ret(Ret::Far)),
+                        0b11001101 => Some(Opcode::Int),
+                        0b11001100 => Some(Opcode::Int3),
+                        0b11001110 => Some(Opcode::Into),
+                        0b11001111 => Some(Opcode::Iret),
+                        0b11010111 => Some(Opcode::Xlat),
+                        0b10001101 => Some(Opcode::Lea),
+                        0b11000101 => Some(Opcode::Lds),
+                        0b11000100 => Some(Opcode::Les),
+                        0b10011111 => Some(Opcode::Lahf),
+                        0b10011110 => Some(Opcode::Sahf),
+                        0b10011000 => Some(Opcode::Cbw),
+                        0b10011001 => Some(Opcode::Cwd),
+                        0b00100111 => Some(Opcode::Daa),
+                        0b00101111 => Some(Opcode::Das),
+                        0b00110111 => Some(Opcode::Aaa),
+                        0b00111111 => Some(Opcode::Aas),
+                        0b11010100 => Some(Opcode::Aam),
+                        0b11010101 => Some(Opcode::Aad),
+                        0b11111000 => Some(Opcode::Clc),
+                        0b11111001 => Some(Opcode::Stc),
+                        0b11110101 => Some(Opcode::Cmc),
+                        0b11111100 => Some(Opcode::Cld),
+                        0b11111101 => Some(Opcode::Std),
+                        0b11111010 => Some(Opcode::Cli),
+                        0b11111011 => Some(Opcode::Sti),
+                        0b11110100 => Some(Opcode::Hlt),
+                        0b10011011 => Some(Opcode::Wait),
+                        // xchg ax, ax does nothing, so it's what nop is encoded as
+                        0b10010000 => Some(Opcode::Nop),
+                        0b10011100 => Some(Opcode::Pushf),
+                        0b10011101 => Some(Opcode::Popf),
+                        _ => match word.high >> 3 {
+                            0b01000 => Some(Opcode::Inc(Inc::Reg)),
+                            0b01001 => Some(Opcode::Dec(Dec::Reg)),
+                            0b01010 => Some(Opcode::Push(Push::Reg)),
+                            0b01011 => Some(Opcode::Pop(Pop::Reg)),
+                            0b10010 => Some(Opcode::Xchg(Xchg::RegWithAcc)),
+                            0b11011 => Some(Opcode::Esc),
+                            _ => match word.high & 0b11100111 {
+                                0b00000110 => Some(Opcode::Push(Push::Seg)),
+                                0b00000111 => Some(Opcode::Pop(Pop::Seg)),
+                                _ => None,
+                            },
+                        },
                     },
                 },
             },
@@ -327,7 +789,7 @@ impl Opcode {
     }
 }
 
-mod mov {
+pub mod mov {
     #[derive(Debug)]
     pub enum Mov {
         RM,
@@ -335,10 +797,12 @@ mod mov {
         ImmToRegOrMem,
         MemToAcc,
         AccToMem,
+        RegOrMemToSeg,
+        SegToRegOrMem,
     }
 }
 
-mod add {
+pub mod add {
     #[derive(Debug)]
     pub enum Add {
         RM,
@@ -347,7 +811,16 @@ mod add {
     }
 }
 
-mod sub {
+pub mod adc {
+    #[derive(Debug)]
+    pub enum Adc {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod sub {
     #[derive(Debug)]
     pub enum Sub {
         RM,
@@ -356,7 +829,16 @@ mod sub {
     }
 }
 
-mod cmp {
+pub mod sbb {
+    #[derive(Debug)]
+    pub enum Sbb {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod cmp {
     #[derive(Debug)]
     pub enum Cmp {
         RM,
@@ -365,7 +847,145 @@ mod cmp {
     }
 }
 
-mod jump {
+pub mod and {
+    #[derive(Debug)]
+    pub enum And {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod or {
+    #[derive(Debug)]
+    pub enum Or {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod xor {
+    #[derive(Debug)]
+    pub enum Xor {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod test {
+    #[derive(Debug)]
+    pub enum Test {
+        RM,
+        ImmToRegOrMem,
+        ImmToAcc,
+    }
+}
+
+pub mod xchg {
+    #[derive(Debug)]
+    pub enum Xchg {
+        RM,
+        RegWithAcc,
+    }
+}
+
+pub mod inc {
+    #[derive(Debug)]
+    pub enum Inc {
+        RegOrMem,
+        Reg,
+    }
+}
+
+pub mod dec {
+    #[derive(Debug)]
+    pub enum Dec {
+        RegOrMem,
+        Reg,
+    }
+}
+
+pub mod shift {
+    #[derive(Debug)]
+    pub enum Shift {
+        Rol,
+        Ror,
+        Rcl,
+        Rcr,
+        Shl, // Sal
+        Shr,
+        Sar,
+    }
+}
+
+pub mod call {
+    #[derive(Debug)]
+    pub enum Call {
+        Direct,
+        DirectFar,
+        Indirect,
+        IndirectFar,
+    }
+}
+
+pub mod jmp {
+    #[derive(Debug)]
+    pub enum Jmp {
+        Short,
+        Direct,
+        DirectFar,
+        Indirect,
+        IndirectFar,
+    }
+}
+
+pub mod ret {
+    #[derive(Debug)]
+    pub enum Ret {
+        Near,
+        Far,
+    }
+}
+
+pub mod push {
+    #[derive(Debug)]
+    pub enum Push {
+        RegOrMem,
+        Reg,
+        Seg,
+    }
+}
+
+pub mod pop {
+    #[derive(Debug)]
+    pub enum Pop {
+        RegOrMem,
+        Reg,
+        Seg,
+    }
+}
+
+pub mod input {
+    #[derive(Debug)]
+    pub enum In {
+        /// The port number is an immediate byte.
+        FixedPort,
+        /// The port number is in DX.
+        VariablePort,
+    }
+}
+
+pub mod output {
+    #[derive(Debug)]
+    pub enum Out {
+        FixedPort,
+        VariablePort,
+    }
+}
+
+pub mod jump {
 
     #[derive(Debug)]
     pub enum J {
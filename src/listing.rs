@@ -0,0 +1,140 @@
+use std::{collections::BTreeSet, fmt::Display};
+
+use crate::{decode::Decoder, Instruction, Opcode, Operand};
+
+/// A whole program disassembled at once, as opposed to [`Decoder`] going an instruction at
+/// a time. Bytes that don't decode are kept as `db` lines so the listing still assembles
+/// back to the same binary, and so are escapes, which NASM has no mnemonic for.
+pub struct Listing {
+    /// Every line along with the offset of its first byte.
+    lines: Vec<(usize, Line)>,
+    /// Offset just past the last byte of the input.
+    end: usize,
+    /// Offsets that get a label, in order, so label_N marks `labels[N]`.
+    labels: Vec<usize>,
+}
+
+enum Line {
+    Instruction(Instruction),
+    Data(u8),
+    /// An escape to a coprocessor, with its bytes.
+    Escape(Instruction, Vec<u8>),
+}
+
+impl Listing {
+    pub fn new(input: &[u8]) -> Self {
+        let lines = Decoder::new(input)
+            .map(|decoded| match decoded {
+                Ok(decoded) if matches!(decoded.instruction.opcode, Opcode::Esc) => (
+                    decoded.offset,
+                    Line::Escape(decoded.instruction, decoded.bytes.to_vec()),
+                ),
+                Ok(decoded) => (decoded.offset, Line::Instruction(decoded.instruction)),
+                // the decoder moves on a byte past anything it can't decode
+                Err(err) => (err.offset, Line::Data(input[err.offset])),
+            })
+            .collect();
+
+        Self {
+            lines,
+            end: input.len(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Like [`Listing::new`], with a second pass that puts a label at every offset a relative
+    /// jump or call lands on and names the targets after them instead of printing `$+n`.
+    pub fn with_labels(input: &[u8]) -> Self {
+        let mut listing = Self::new(input);
+
+        // a label can only go between lines, targets in the middle of an instruction or
+        // outside of the program keep their $+n
+        let boundaries: BTreeSet<usize> = listing
+            .lines
+            .iter()
+            .map(|(offset, _)| *offset)
+            .chain([listing.end])
+            .collect();
+
+        let targets: BTreeSet<usize> = (0..listing.lines.len())
+            .filter_map(|n| listing.jump_target(n))
+            .filter(|target| boundaries.contains(target))
+            .collect();
+        listing.labels = targets.into_iter().collect();
+
+        for n in 0..listing.lines.len() {
+            let Some(label) = listing
+                .jump_target(n)
+                .and_then(|target| listing.labels.binary_search(&target).ok())
+            else {
+                continue;
+            };
+
+            if let (_, Line::Instruction(i)) = &mut listing.lines[n] {
+                i.destination = Some(Operand::Label(label));
+            }
+        }
+
+        listing
+    }
+
+    /// Where the relative jump or call on line `n` goes to, relative increments count from
+    /// the end of the instruction.
+    fn jump_target(&self, n: usize) -> Option<usize> {
+        let (_, Line::Instruction(i)) = &self.lines[n] else {
+            return None;
+        };
+
+        let increment = match &i.destination {
+            Some(Operand::InstPtrIncrement(inc)) => *inc as isize,
+            Some(Operand::NearInstPtrIncrement(inc)) => *inc as isize,
+            _ => return None,
+        };
+
+        let next = self
+            .lines
+            .get(n + 1)
+            .map_or(self.end, |(offset, _)| *offset);
+        next.checked_add_signed(increment)
+    }
+
+    fn fmt_label(&self, f: &mut std::fmt::Formatter<'_>, offset: usize) -> std::fmt::Result {
+        match self.labels.binary_search(&offset) {
+            Ok(n) => writeln!(f, "label_{}:", n),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl Display for Listing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut data: Vec<String> = Vec::new();
+
+        for (n, (offset, line)) in self.lines.iter().enumerate() {
+            self.fmt_label(f, *offset)?;
+
+            match line {
+                Line::Instruction(i) => writeln!(f, "{}", i)?,
+                Line::Escape(i, bytes) => {
+                    let bytes: Vec<String> = bytes.iter().map(|b| format!("{:#04x}", b)).collect();
+                    writeln!(f, "db {} ; {}", bytes.join(", "), i)?;
+                }
+                Line::Data(byte) => {
+                    data.push(format!("{:#04x}", byte));
+
+                    // runs of data bytes share a db line, up to a label or 16 bytes
+                    let run_ends = match self.lines.get(n + 1) {
+                        Some((next, Line::Data(_))) => self.labels.binary_search(next).is_ok(),
+                        _ => true,
+                    };
+                    if run_ends || data.len() == 16 {
+                        writeln!(f, "db {}", data.join(", "))?;
+                        data.clear();
+                    }
+                }
+            }
+        }
+
+        self.fmt_label(f, self.end)
+    }
+}
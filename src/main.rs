@@ -1,15 +1,21 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use sim8086::{
-    decode::Decoder,
-    exec::{clock_est::ClockEstimate, Executor},
+    asm::{assemble, Assembly},
+    exec::{clock_est::ClockEstimate, Executor, Status, LOAD_SEGMENT},
+    listing::Listing,
 };
-use std::{fs, path::PathBuf};
+use std::{fs, path::PathBuf, process::ExitCode};
 
 #[derive(Parser)]
 #[command(author, version, about)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
-    // path to a binary file
-    path: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    // path to a binary file, or to NASM source if it ends in .asm
+    #[arg(required = true)]
+    path: Option<PathBuf>,
 
     #[arg(short, long)]
     exec: bool,
@@ -17,21 +23,105 @@ struct Cli {
     #[arg(short, long, requires = "exec")]
     dump: bool,
 
+    /// Name jump targets with labels instead of printing them as $+n
+    #[arg(short, long, conflicts_with = "exec")]
+    labels: bool,
+
     /// Show clock cycle estimates for each instructions
     #[arg(short, long)]
     clock_estimate: bool,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+#[derive(Subcommand)]
+enum Command {
+    /// Assemble a NASM source file into a flat binary
+    Asm {
+        path: PathBuf,
+
+        /// Where to write the binary, the source path without its extension by default
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    let buffer = fs::read(cli.path)?;
+    // errors are printed for people, the Debug output main would use is for programmers
+    if let Err(err) = run(cli) {
+        eprintln!("error: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(Command::Asm { path, output }) = cli.command {
+        let source = fs::read_to_string(&path)?;
+        let binary = assemble(&source)?;
+
+        // like nasm, the binary goes next to the source
+        let output = output.unwrap_or_else(|| path.with_extension(""));
+        fs::write(output, binary)?;
+
+        return Ok(());
+    }
+
+    let path = cli
+        .path
+        .expect("clap to require a path without a subcommand");
+
+    // source is assembled in memory, and the lines are kept around to show in the trace
+    let assembly = match path.extension() {
+        Some(extension) if extension == "asm" => Some(Assembly::new(&fs::read_to_string(&path)?)?),
+        _ => None,
+    };
+
+    let buffer = match &assembly {
+        Some(assembly) => assembly.binary.clone(),
+        None => fs::read(path)?,
+    };
 
     if cli.exec {
-        let mut exe = Executor::new(Decoder::new(&buffer));
+        let mut exe = Executor::new(&buffer)?;
+
+        let source_width = assembly
+            .iter()
+            .flat_map(|assembly| &assembly.lines)
+            .map(|line| line.text.len())
+            .max()
+            .unwrap_or_default();
+
+        // the trace has physical addresses, the assembly offsets from the start of the program
+        let load_address = (LOAD_SEGMENT as usize) << 4;
 
         let mut clock_estimate_sum = 0;
-        while let Some((i, diff)) = exe.execute_next() {
+        let mut halted = false;
+        loop {
+            let address = exe.next_address() as usize;
+            let Some((i, diff, status)) = exe.execute_next()? else {
+                break;
+            };
+            halted = status == Status::Halted;
+
+            // code that was never assembled, like something the program wrote itself, gets
+            // blank columns
+            if let Some(assembly) = &assembly {
+                let line = address
+                    .checked_sub(load_address)
+                    .and_then(|offset| assembly.line_at(offset));
+                match line {
+                    Some(line) => print!(
+                        "{:>4} | {:<width$} | ",
+                        line.number,
+                        line.text,
+                        width = source_width
+                    ),
+                    None => print!("{:>4} | {:<width$} | ", "", "", width = source_width),
+                }
+            }
+
             if cli.clock_estimate {
                 print!("{}", i);
                 let curr_est = ClockEstimate::from(i);
@@ -53,6 +143,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
+        if halted {
+            println!("\nHalted");
+        }
+
         println!("\nFinal registers:");
         println!("{:#?}", exe.registers);
 
@@ -63,11 +157,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         println!("bits 16\n");
 
-        let mut decoder = Decoder::new(&buffer);
+        let listing = if cli.labels {
+            Listing::with_labels(&buffer)
+        } else {
+            Listing::new(&buffer)
+        };
 
-        while let Some(i) = decoder.decode_next() {
-            println!("{}", i);
-        }
+        print!("{}", listing);
     }
 
     Ok(())
@@ -0,0 +1,229 @@
+use sim8086::asm::{assemble, AsmErrorReason, Assembly};
+
+fn error(source: &str) -> (usize, AsmErrorReason) {
+    let err = assemble(source).unwrap_err();
+    (err.line, err.reason)
+}
+
+#[test]
+fn picks_the_forms_nasm_does() {
+    let source = "
+bits 16
+add ax, 7
+add ax, 1000
+add al, 5
+sub bx, -2
+cmp ax, 65535
+test ax, 16
+test [bx + si], dx
+mov [1002], al
+mov ax, [es:16]
+inc cx
+xchg cx, ax
+push ds
+shl word [bp + 4], cl
+int 3
+int3
+";
+
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x83, 0xc0, 0x07, // sign extended byte rather than the accumulator form
+            0x05, 0xe8, 0x03, // unless it doesn't fit
+            0x04, 0x05, //
+            0x83, 0xeb, 0xfe, //
+            0x83, 0xf8, 0xff, //
+            0xa9, 0x10, 0x00, // test can't sign extend
+            0x85, 0x10, //
+            0xa2, 0xea, 0x03, //
+            0x26, 0xa1, 0x10, 0x00, //
+            0x41, //
+            0x91, //
+            0x1e, //
+            0xd3, 0x66, 0x04, //
+            0xcd, 0x03, //
+            0xcc,
+        ]
+    );
+}
+
+/// Every fixture has a reference binary checked in next to it, made by another assembler.
+#[test]
+fn assembles_the_fixtures_like_the_reference_binaries() {
+    for dir in ["./fixtures", "./fixtures/decode", "./fixtures/exec"] {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("asm".as_ref()) {
+                continue;
+            }
+
+            let source = std::fs::read_to_string(&path).unwrap();
+            let expected = std::fs::read(path.with_extension("bin")).unwrap();
+
+            let binary = assemble(&source).unwrap_or_else(|err| panic!("{}", err));
+            assert_eq!(binary, expected, "{}", path.display());
+        }
+    }
+}
+
+/// The reference binaries weren't made by NASM itself, see fixtures/README.md. This checks
+/// them against the real thing where it's installed.
+#[test]
+#[ignore = "needs nasm on the path"]
+fn reference_binaries_match_nasm() {
+    let output = std::env::temp_dir().join("sim8086_reference_nasm");
+
+    for dir in ["./fixtures", "./fixtures/decode", "./fixtures/exec"] {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() != Some("asm".as_ref()) {
+                continue;
+            }
+
+            let status = std::process::Command::new("nasm")
+                .arg(&path)
+                .arg("-o")
+                .arg(&output)
+                .status()
+                .unwrap();
+            assert!(status.success(), "nasm {}", path.display());
+
+            let expected = std::fs::read(path.with_extension("bin")).unwrap();
+            assert_eq!(
+                std::fs::read(&output).unwrap(),
+                expected,
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    std::fs::remove_file(output).unwrap();
+}
+
+#[test]
+fn labels_and_expressions() {
+    let source = "
+start:
+mov bp, 64*4
+jmp done
+jmp start
+jmp near done
+call 1:far_routine-16
+db 0x90, 1
+dw start, done - start
+far_routine:
+done:
+";
+
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0xbd, 0x00, 0x01, //
+            0xeb, 0x10, //
+            0xeb, 0xf9, //
+            0xe9, 0x0b, 0x00, //
+            0x9a, 0x05, 0x00, 0x01, 0x00, //
+            0x90, 0x01, //
+            0x00, 0x00, 0x15, 0x00,
+        ]
+    );
+}
+
+#[test]
+fn jmp_goes_near_when_short_doesnt_reach() {
+    let source = format!(
+        "jmp far_away\n{}far_away:\njmp far_away\n",
+        "nop\n".repeat(130)
+    );
+    let binary = assemble(&source).unwrap();
+
+    assert_eq!(binary[..3], [0xe9, 0x82, 0x00]);
+    assert_eq!(binary[binary.len() - 2..], [0xeb, 0xfe]);
+}
+
+#[test]
+fn word_jumps_are_near() {
+    assert_eq!(assemble("jmp word 0x100").unwrap(), [0xe9, 0xfd, 0x00]);
+    assert_eq!(assemble("call word 0x100").unwrap(), [0xe8, 0xfd, 0x00]);
+}
+
+#[test]
+fn errors_name_the_line() {
+    assert_eq!(
+        error("bits 16\nmov ax, bx\njmp nowhere\n"),
+        (3, AsmErrorReason::UnknownLabel)
+    );
+    assert_eq!(error("mvo ax, bx"), (1, AsmErrorReason::UnknownMnemonic));
+    assert_eq!(error("mov ax, [bx"), (1, AsmErrorReason::Syntax));
+    assert_eq!(error("mov ax, bl"), (1, AsmErrorReason::InvalidOperands));
+    assert_eq!(error("inc [bx]"), (1, AsmErrorReason::SizeNotSpecified));
+    assert_eq!(error("mov al, 300"), (1, AsmErrorReason::OutOfRange));
+    assert_eq!(error("a:\nnop\na:\n"), (3, AsmErrorReason::DuplicateLabel));
+    assert_eq!(error("mov al, 'a"), (1, AsmErrorReason::Syntax));
+    assert_eq!(
+        error("a:\ncall short a"),
+        (2, AsmErrorReason::InvalidOperands)
+    );
+    assert_eq!(error("jmp $+70000"), (1, AsmErrorReason::OutOfRange));
+
+    let too_far = format!("je far_away\n{}far_away:\n", "nop\n".repeat(128));
+    assert_eq!(error(&too_far), (1, AsmErrorReason::OutOfRange));
+}
+
+#[test]
+fn lines_map_back_to_the_source() {
+    let source = "bits 16\n\nstart: ; nothing here\nmov ax, 1 ; one\ndb 1, 2\njmp start\n";
+    let assembly = Assembly::new(source).unwrap();
+
+    let line_at = |offset| {
+        assembly
+            .line_at(offset)
+            .map(|l| (l.number, l.text.as_str()))
+    };
+    assert_eq!(line_at(0), Some((4, "mov ax, 1")));
+    assert_eq!(line_at(2), Some((4, "mov ax, 1")));
+    assert_eq!(line_at(4), Some((5, "db 1, 2")));
+    assert_eq!(line_at(5), Some((6, "jmp start")));
+    assert_eq!(line_at(7), None);
+}
+
+#[test]
+fn character_literals() {
+    let source = "
+mov al, 'a'
+mov ax, 'ab'
+cmp al, \";\" ; quoted semicolons don't start a comment
+db 'hi', 0
+dw 'abc'
+";
+
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0xb0, 0x61, //
+            0xb8, 0x61, 0x62, // the first character goes in the low byte
+            0x3c, 0x3b, //
+            0x68, 0x69, 0x00, //
+            0x61, 0x62, 0x63, 0x00, // padded to a whole word
+        ]
+    );
+}
+
+#[test]
+fn prefixes_on_their_own_line() {
+    let source = "
+rep
+movsb
+lock
+inc word [bx]
+es
+lodsb
+";
+
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0xf3, 0xa4, 0xf0, 0xff, 0x07, 0x26, 0xac]
+    );
+}
@@ -1,26 +1,51 @@
-use std::process::Command;
-
 use insta::assert_display_snapshot;
 
 #[test]
 fn estimating_cycles() {
-    let _ = Command::new("nasm")
-        .arg("./fixtures/estimating_cycles.asm")
-        .status()
+    let app_output = assert_cmd::Command::cargo_bin("sim8086")
+        .unwrap()
+        .arg("./fixtures/estimating_cycles.bin")
+        .arg("--exec")
+        .arg("-c")
+        .output()
+        .map(|out| String::from_utf8(out.stdout).unwrap())
         .unwrap();
 
+    insta::with_settings!({ description => "estimating_cycles" }, {
+        assert_display_snapshot!(app_output);
+    });
+}
+
+#[test]
+fn estimating_variable_cycles() {
     let app_output = assert_cmd::Command::cargo_bin("sim8086")
         .unwrap()
-        .arg("./fixtures/estimating_cycles")
+        .arg("./fixtures/estimating_variable_cycles.bin")
         .arg("--exec")
         .arg("-c")
         .output()
         .map(|out| String::from_utf8(out.stdout).unwrap())
         .unwrap();
 
-    insta::with_settings!({ description => "estimating_cycles" }, {
+    insta::with_settings!({ description => "estimating_variable_cycles" }, {
         assert_display_snapshot!(app_output);
     });
+}
+
+#[test]
+fn every_exec_fixture_gets_estimates() {
+    for entry in std::fs::read_dir("./fixtures/exec").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("bin".as_ref()) {
+            continue;
+        }
 
-    std::fs::remove_file("./fixtures/estimating_cycles").unwrap();
+        assert_cmd::Command::cargo_bin("sim8086")
+            .unwrap()
+            .arg(&path)
+            .arg("--exec")
+            .arg("-c")
+            .assert()
+            .success();
+    }
 }
@@ -1,17 +1,15 @@
-use std::{io::Write, process::Command};
+use std::io::Write;
 
 use insta::assert_display_snapshot;
 
+/// Disassembles the reference binary checked in next to a fixture, assembles the listing again
+/// and checks that it comes out the same as the reference.
 macro_rules! test_with {
-    ($file:literal) => {
-        let _ = Command::new("nasm")
-            .arg(format!("./fixtures/decode/{}.asm", $file))
-            .status()
-            .unwrap();
-
+    ($file:literal $(, $arg:literal)*) => {
         let app_output = assert_cmd::Command::cargo_bin("sim8086")
             .unwrap()
-            .arg(format!("./fixtures/decode/{}", $file))
+            .arg(format!("./fixtures/decode/{}.bin", $file))
+            $(.arg($arg))*
             .output()
             .map(|out| String::from_utf8(out.stdout).unwrap())
             .unwrap();
@@ -22,24 +20,69 @@ macro_rules! test_with {
 
         write!(test_file, "{}", app_output).unwrap();
 
-        let _ = Command::new("nasm").arg(&test_file_path).status().unwrap();
-
-        assert_cmd::Command::new("diff")
-            .arg(format!("./fixtures/decode/{}", $file))
-            .arg(format!("./fixtures/decode/{}_test", $file))
+        assert_cmd::Command::cargo_bin("sim8086")
+            .unwrap()
+            .arg("asm")
+            .arg(&test_file_path)
             .assert()
             .success();
 
+        let expected = std::fs::read(format!("./fixtures/decode/{}.bin", $file)).unwrap();
+        let reassembled = std::fs::read(format!("./fixtures/decode/{}_test", $file)).unwrap();
+
         std::fs::remove_file(test_file_path).unwrap();
-        std::fs::remove_file(format!("./fixtures/decode/{}", $file)).unwrap();
         std::fs::remove_file(format!("./fixtures/decode/{}_test", $file)).unwrap();
 
+        assert_eq!(reassembled, expected, "{}", $file);
+
         insta::with_settings!({ description => $file }, {
             assert_display_snapshot!(app_output);
         })
     };
 }
 
+/// Like the reassembly in `test_with!`, but with NASM, for every decode fixture. Needs nasm on
+/// the path, so it only runs with `cargo test -- --ignored`.
+#[test]
+#[ignore = "needs nasm on the path"]
+fn nasm_reassembles_the_listings() {
+    let listing = std::env::temp_dir().join("sim8086_listing.asm");
+    let output = std::env::temp_dir().join("sim8086_listing");
+
+    for entry in std::fs::read_dir("./fixtures/decode").unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("bin".as_ref()) {
+            continue;
+        }
+
+        let app_output = assert_cmd::Command::cargo_bin("sim8086")
+            .unwrap()
+            .arg(&path)
+            .output()
+            .unwrap();
+        std::fs::write(&listing, app_output.stdout).unwrap();
+
+        let status = std::process::Command::new("nasm")
+            .arg(&listing)
+            .arg("-o")
+            .arg(&output)
+            .status()
+            .unwrap();
+        assert!(status.success(), "nasm {}", path.display());
+
+        let expected = std::fs::read(&path).unwrap();
+        assert_eq!(
+            std::fs::read(&output).unwrap(),
+            expected,
+            "{}",
+            path.display()
+        );
+    }
+
+    std::fs::remove_file(listing).unwrap();
+    std::fs::remove_file(output).unwrap();
+}
+
 #[test]
 fn movs() {
     test_with!("many_register_mov");
@@ -47,6 +90,8 @@ fn movs() {
     test_with!("more_movs");
 
     test_with!("challenge_movs");
+
+    test_with!("acc_movs");
 }
 
 #[test]
@@ -54,25 +99,124 @@ fn add_sub_cmp() {
     test_with!("add_sub_cmp");
 }
 
+#[test]
+fn adc_sbb() {
+    test_with!("adc_sbb");
+}
+
+#[test]
+fn logical() {
+    test_with!("logical");
+}
+
+#[test]
+fn shifts() {
+    test_with!("shifts");
+}
+
+#[test]
+fn mul_div() {
+    test_with!("mul_div");
+}
+
+#[test]
+fn inc_dec() {
+    test_with!("inc_dec");
+}
+
+/// Relative jumps are printed as `$+n` displacements, which nasm doesn't reassemble to the same
+/// bytes, so these only get a snapshot.
+macro_rules! snapshot_with {
+    ($file:literal) => {
+        let app_output = assert_cmd::Command::cargo_bin("sim8086")
+            .unwrap()
+            .arg(format!("./fixtures/decode/{}.bin", $file))
+            .output()
+            .map(|out| String::from_utf8(out.stdout).unwrap())
+            .unwrap();
+
+        insta::with_settings!({ description => $file }, {
+            assert_display_snapshot!(app_output);
+        })
+    };
+}
+
 #[test]
 fn jumps() {
-    let file = "jnz";
+    snapshot_with!("jnz");
+}
+
+#[test]
+fn near_jumps() {
+    snapshot_with!("near_jumps");
+}
 
-    let _ = Command::new("nasm")
-        .arg(format!("./fixtures/decode/{}.asm", file))
-        .status()
-        .unwrap();
+#[test]
+fn segments() {
+    test_with!("segments");
+}
 
-    let app_output = assert_cmd::Command::cargo_bin("sim8086")
-        .unwrap()
-        .arg(format!("./fixtures/decode/{}", file))
-        .output()
-        .map(|out| String::from_utf8(out.stdout).unwrap())
-        .unwrap();
+#[test]
+fn stack() {
+    test_with!("stack");
+}
 
-    std::fs::remove_file(format!("./fixtures/decode/{}", file)).unwrap();
+#[test]
+fn calls_and_rets() {
+    test_with!("calls_and_rets");
+}
+
+#[test]
+fn strings() {
+    test_with!("strings");
+}
+
+#[test]
+fn string_overrides() {
+    test_with!("string_overrides");
+}
+
+#[test]
+fn interrupts() {
+    test_with!("interrupts");
+}
 
-    insta::with_settings!({ description => file }, {
-        assert_display_snapshot!(app_output);
-    })
+#[test]
+fn data_transfer() {
+    test_with!("data_transfer");
+}
+
+#[test]
+fn bcd() {
+    test_with!("bcd");
+}
+
+#[test]
+fn processor_control() {
+    test_with!("processor_control");
+}
+
+#[test]
+fn escape() {
+    test_with!("escape");
+}
+
+#[test]
+fn in_out() {
+    test_with!("in_out");
+}
+
+#[test]
+fn data_bytes() {
+    test_with!("data_bytes");
+}
+
+#[test]
+fn single_byte_end() {
+    test_with!("single_byte_end");
+}
+
+#[test]
+fn labels() {
+    test_with!("labels", "--labels");
 }
@@ -0,0 +1,107 @@
+use sim8086::decode::{DecodeError, DecodeErrorReason, Decoder};
+
+fn first_error(program: &[u8]) -> DecodeError {
+    let mut decoder = Decoder::new(program);
+
+    loop {
+        match decoder.decode_next() {
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("{:02x?} to fail to decode", program),
+            Err(err) => return err,
+        }
+    }
+}
+
+#[test]
+fn unknown_opcode() {
+    let err = first_error(&[
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0xc1, 0x00, // a hole in the opcode map
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::UnknownOpcode);
+    assert_eq!(err.offset, 3);
+    assert_eq!(err.bytes, [0xc1]);
+    assert_eq!(err.to_string(), "unknown opcode 0xC1 at offset 0x3");
+}
+
+#[test]
+fn invalid_mod_rm() {
+    let err = first_error(&[
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0x8d, 0xc0, // lea ax, ax
+        0xb8, 0x02, 0x00, // mov ax, 2
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::InvalidModRm);
+    assert_eq!(err.offset, 3);
+    assert_eq!(err.bytes, [0x8d, 0xc0]);
+    assert_eq!(err.to_string(), "invalid mod/rm in 0x8D 0xC0 at offset 0x3");
+}
+
+#[test]
+fn invalid_group_member() {
+    // FE only has inc and dec, /2 and up are word-only or unused
+    let err = first_error(&[
+        0xfe, 0xd0, // call al
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::InvalidModRm);
+    assert_eq!(err.offset, 0);
+    assert_eq!(err.bytes, [0xfe, 0xd0]);
+
+    for reg in 0b010..=0b111 {
+        let mod_rm = 0b11_000_000 | reg << 3;
+        assert_eq!(
+            first_error(&[0xfe, mod_rm]).reason,
+            DecodeErrorReason::InvalidModRm,
+            "FE /{}",
+            reg
+        );
+    }
+
+    // F7 /1 is a hole in a group as well
+    assert_eq!(
+        first_error(&[0xf7, 0xc8]).reason,
+        DecodeErrorReason::InvalidModRm
+    );
+}
+
+#[test]
+fn truncated() {
+    let err = first_error(&[
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0x8b, 0x87, 0xe8, // mov ax, [bx + 1000] without the high byte of 1000
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::Truncated);
+    assert_eq!(err.offset, 3);
+    assert_eq!(err.bytes, [0x8b, 0x87, 0xe8]);
+    assert_eq!(
+        err.to_string(),
+        "truncated instruction 0x8B 0x87 0xE8 at offset 0x3"
+    );
+}
+
+#[test]
+fn prefix_without_instruction() {
+    let err = first_error(&[
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0xf3, // rep
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::Truncated);
+    assert_eq!(err.offset, 3);
+    assert_eq!(err.bytes, [0xf3]);
+}
+
+#[test]
+fn missing_mod_rm() {
+    let err = first_error(&[
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0x89, // mov with nothing to say what to or from
+    ]);
+
+    assert_eq!(err.reason, DecodeErrorReason::Truncated);
+    assert_eq!(err.bytes, [0x89]);
+}
@@ -0,0 +1,62 @@
+use sim8086::decode::{DecodeErrorReason, Decoder};
+
+#[test]
+fn offsets_lengths_and_bytes() {
+    let program = [
+        0xb8, 0x01, 0x00, // mov ax, 1
+        0x26, 0x8b, 0x0e, 0x08, 0x00, // mov cx, [es:8]
+        0xf3, 0xa4, // rep movsb
+        0x90, // nop
+    ];
+
+    let decoded = Decoder::new(&program)
+        .map(|d| {
+            d.map(|d| {
+                (
+                    d.offset,
+                    d.encoded_len(),
+                    d.bytes,
+                    d.instruction.to_string(),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        decoded,
+        [
+            (0, 3, &program[0..3], "mov ax, 1".to_string()),
+            (3, 5, &program[3..8], "mov cx, [es:8]".to_string()),
+            (8, 2, &program[8..10], "rep movsb".to_string()),
+            (10, 1, &program[10..], "nop".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn iteration_carries_on_past_errors() {
+    let program = [
+        0xc1, // a hole in the opcode map
+        0x8d, 0xc0, // lea ax, ax
+        0x40, // inc ax
+    ];
+
+    let decoded = Decoder::new(&program)
+        .map(|d| match d {
+            Ok(d) => Ok((d.offset, d.instruction.to_string())),
+            Err(err) => Err((err.offset, err.reason)),
+        })
+        .collect::<Vec<_>>();
+
+    // only the first byte of lea is skipped, its mod/rm byte is tried as an opcode next
+    assert_eq!(
+        decoded,
+        [
+            Err((0, DecodeErrorReason::UnknownOpcode)),
+            Err((1, DecodeErrorReason::InvalidModRm)),
+            Err((2, DecodeErrorReason::UnknownOpcode)),
+            Ok((3, "inc ax".to_string())),
+        ]
+    );
+}
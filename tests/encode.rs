@@ -0,0 +1,154 @@
+use sim8086::{
+    decode::Decoder,
+    encode::{encode, EncodeErrorReason},
+    mov::Mov,
+    EffectiveAddressCalc, Instruction, Opcode, Operand, Register, Repeat,
+};
+
+/// Decodes the reference binary of a decode fixture and encodes every instruction again, which
+/// should give back exactly the same bytes.
+fn round_trip(file: &str) {
+    let input = std::fs::read(format!("./fixtures/decode/{}.bin", file)).unwrap();
+
+    let mut decoder = Decoder::new(&input);
+    let mut output = Vec::new();
+    while let Some(i) = decoder.decode_next().unwrap() {
+        let bytes = encode(&i).unwrap_or_else(|err| panic!("{}", err));
+
+        // a mismatch is easiest to spot next to the instruction it's in
+        let start = output.len();
+        let end = (start + bytes.len()).min(input.len());
+        assert_eq!(bytes, input[start..end], "{} in {}", i, file);
+
+        output.extend(bytes);
+    }
+
+    assert_eq!(output, input);
+}
+
+#[test]
+fn movs() {
+    round_trip("single_register_mov");
+    round_trip("many_register_mov");
+    round_trip("more_movs");
+    round_trip("challenge_movs");
+    round_trip("acc_movs");
+}
+
+#[test]
+fn arithmetic() {
+    round_trip("add_sub_cmp");
+    round_trip("adc_sbb");
+    round_trip("mul_div");
+    round_trip("inc_dec");
+    round_trip("bcd");
+}
+
+#[test]
+fn logical() {
+    round_trip("logical");
+    round_trip("shifts");
+}
+
+#[test]
+fn control_transfer() {
+    round_trip("jnz");
+    round_trip("near_jumps");
+    round_trip("calls_and_rets");
+    round_trip("interrupts");
+}
+
+#[test]
+fn everything_else() {
+    round_trip("segments");
+    round_trip("stack");
+    round_trip("strings");
+    round_trip("string_overrides");
+    round_trip("data_transfer");
+    round_trip("processor_control");
+    round_trip("escape");
+    round_trip("in_out");
+    round_trip("single_byte_end");
+}
+
+/// Decodes a single instruction and encodes it again.
+fn reencode(bytes: &[u8]) -> Vec<u8> {
+    let i = Decoder::new(bytes).decode_next().unwrap().unwrap();
+    encode(&i).unwrap()
+}
+
+#[test]
+fn keeps_decoded_d_and_s() {
+    // mov ax, bx both ways around
+    assert_eq!(reencode(&[0x89, 0xd8]), [0x89, 0xd8]);
+    assert_eq!(reencode(&[0x8b, 0xc3]), [0x8b, 0xc3]);
+
+    // add bx, 5 with and without sign extension
+    assert_eq!(
+        reencode(&[0x81, 0xc3, 0x05, 0x00]),
+        [0x81, 0xc3, 0x05, 0x00]
+    );
+    assert_eq!(reencode(&[0x83, 0xc3, 0x05]), [0x83, 0xc3, 0x05]);
+    assert_eq!(
+        reencode(&[0x81, 0xc3, 0xff, 0xff]),
+        [0x81, 0xc3, 0xff, 0xff]
+    );
+}
+
+#[test]
+fn canonical_encodings() {
+    // mov ax, [bx + 5] with a word displacement gets the byte one
+    assert_eq!(reencode(&[0x8b, 0x87, 0x05, 0x00]), [0x8b, 0x47, 0x05]);
+
+    // 82 is the same as 80, S means nothing for a byte
+    assert_eq!(reencode(&[0x82, 0xc3, 0x05]), [0x80, 0xc3, 0x05]);
+
+    // only the last segment override counts, and it goes after rep
+    assert_eq!(reencode(&[0x26, 0x2e, 0xf3, 0xa4]), [0xf3, 0x2e, 0xa4]);
+}
+
+#[test]
+fn built_from_scratch() {
+    let mov = Instruction::new(Opcode::Mov(Mov::ImmToReg), true)
+        .with_destination(Operand::Register(Register::CX))
+        .with_source(Operand::Immediate(12));
+    assert_eq!(encode(&mov).unwrap(), [0xb9, 0x0c, 0x00]);
+
+    let mov = Instruction::new(Opcode::Mov(Mov::RM), false)
+        .with_destination(Operand::MemoryAddress(EffectiveAddressCalc::SingleRegPlus(
+            Register::BP,
+            -2,
+        )))
+        .with_source(Operand::Register(Register::AH))
+        .with_segment_override(Register::ES);
+    assert_eq!(encode(&mov).unwrap(), [0x26, 0x88, 0x66, 0xfe]);
+
+    let movs = Instruction::new(Opcode::Movs, true).with_repeat(Repeat::Rep);
+    assert_eq!(encode(&movs).unwrap(), [0xf3, 0xa5]);
+
+    // without a decoded D bit, a register source goes in REG
+    let mov = Instruction::new(Opcode::Mov(Mov::RM), true)
+        .with_destination(Operand::Register(Register::AX))
+        .with_source(Operand::Register(Register::BX));
+    assert_eq!(encode(&mov).unwrap(), [0x89, 0xd8]);
+}
+
+#[test]
+fn operands_have_to_fit_the_form() {
+    let reason = |i: Instruction| encode(&i).unwrap_err().reason;
+
+    // registers have to be as wide as W
+    let mov = Instruction::new(Opcode::Mov(Mov::ImmToReg), true)
+        .with_destination(Operand::Register(Register::CL))
+        .with_source(Operand::Immediate(12));
+    assert_eq!(reason(mov), EncodeErrorReason::InvalidOperands);
+
+    let mov = Instruction::new(Opcode::Mov(Mov::RM), false)
+        .with_destination(Operand::Register(Register::AX))
+        .with_source(Operand::Register(Register::BL));
+    assert_eq!(reason(mov), EncodeErrorReason::InvalidOperands);
+
+    // only segment registers make an override prefix
+    let movs = Instruction::new(Opcode::Movs, false).with_segment_override(Register::BX);
+    assert_eq!(reason(movs), EncodeErrorReason::InvalidOperands);
+}
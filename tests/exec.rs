@@ -1,17 +1,11 @@
-use std::process::Command;
-
 use insta::assert_display_snapshot;
 
+/// Runs the reference binary checked in next to a fixture.
 macro_rules! test_with {
     ($file:literal) => {
-        let _ = Command::new("nasm")
-            .arg(format!("./fixtures/exec/{}.asm", $file))
-            .status()
-            .unwrap();
-
         let app_output = assert_cmd::Command::cargo_bin("sim8086")
             .unwrap()
-            .arg(format!("./fixtures/exec/{}", $file))
+            .arg(format!("./fixtures/exec/{}.bin", $file))
             .arg("--exec")
             .output()
             .map(|out| String::from_utf8(out.stdout).unwrap())
@@ -20,8 +14,6 @@ macro_rules! test_with {
         insta::with_settings!({ description => $file }, {
             assert_display_snapshot!(app_output);
         });
-
-        std::fs::remove_file(format!("./fixtures/exec/{}", $file)).unwrap();
     };
 }
 
@@ -31,11 +23,21 @@ fn movs() {
     test_with!("register_movs");
 }
 
+#[test]
+fn byte_registers() {
+    test_with!("byte_registers");
+}
+
 #[test]
 fn add_sub_cmp() {
     test_with!("add_sub_cmp");
 }
 
+#[test]
+fn flags() {
+    test_with!("flags");
+}
+
 #[test]
 fn ip_tracking() {
     test_with!("ip_register");
@@ -46,6 +48,11 @@ fn conditional_jumps() {
     test_with!("conditional_jumps");
 }
 
+#[test]
+fn loops_and_jumps() {
+    test_with!("loops_and_jumps");
+}
+
 #[test]
 fn memory_movs() {
     test_with!("memory_mov");
@@ -61,3 +68,114 @@ fn memory_add_loop() {
 fn draw_rectangle_img() {
     test_with!("draw_rectangle");
 }
+
+#[test]
+fn logical() {
+    test_with!("logical");
+}
+
+#[test]
+fn shifts() {
+    test_with!("shifts");
+}
+
+#[test]
+fn mul_div() {
+    test_with!("mul_div");
+}
+
+#[test]
+fn inc_dec() {
+    test_with!("inc_dec");
+}
+
+#[test]
+fn memory_widths() {
+    test_with!("memory_widths");
+}
+
+#[test]
+fn segments() {
+    test_with!("segments");
+}
+
+#[test]
+fn stack() {
+    test_with!("stack");
+}
+
+#[test]
+fn calls() {
+    test_with!("calls");
+}
+
+#[test]
+fn strings() {
+    test_with!("strings");
+}
+
+#[test]
+fn interrupts() {
+    test_with!("interrupts");
+}
+
+#[test]
+fn data_transfer() {
+    test_with!("data_transfer");
+}
+
+#[test]
+fn bcd() {
+    test_with!("bcd");
+}
+
+#[test]
+fn processor_control() {
+    test_with!("processor_control");
+}
+
+#[test]
+fn in_out() {
+    test_with!("in_out");
+}
+
+#[test]
+fn outside_the_program() {
+    test_with!("outside_the_program");
+}
+
+#[test]
+fn past_the_end() {
+    test_with!("past_the_end");
+}
+
+#[test]
+fn source_lines() {
+    // no separate assembly step, the source is run as is
+    let app_output = assert_cmd::Command::cargo_bin("sim8086")
+        .unwrap()
+        .arg("./fixtures/exec/loops_and_jumps.asm")
+        .arg("--exec")
+        .output()
+        .map(|out| String::from_utf8(out.stdout).unwrap())
+        .unwrap();
+
+    insta::with_settings!({ description => "loops_and_jumps.asm" }, {
+        assert_display_snapshot!(app_output);
+    });
+}
+
+#[test]
+fn program_too_large() {
+    use sim8086::exec::{Executor, ProgramTooLarge, LOAD_SEGMENT};
+
+    let available = (1 << 20) - ((LOAD_SEGMENT as usize) << 4);
+    assert!(Executor::new(&vec![0x90; available]).is_ok());
+    assert_eq!(
+        Executor::new(&vec![0x90; available + 1]).err(),
+        Some(ProgramTooLarge {
+            size: available + 1,
+            available
+        })
+    );
+}
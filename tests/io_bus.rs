@@ -0,0 +1,45 @@
+use sim8086::exec::{io::IoBus, Executor};
+
+/// A console port that collects what's written to it, and a status port that's always ready.
+#[derive(Default)]
+struct Console {
+    output: std::rc::Rc<std::cell::RefCell<Vec<u8>>>,
+}
+
+impl IoBus for Console {
+    fn read(&mut self, port: u16, _wide: bool) -> u16 {
+        match port {
+            0x3fd => 0x20,
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, port: u16, value: u16, _wide: bool) {
+        if port == 0x3f8 {
+            self.output.borrow_mut().push(value as u8);
+        }
+    }
+}
+
+#[test]
+fn custom_bus() {
+    let program = [
+        0xba, 0xfd, 0x03, // mov dx, 0x3fd
+        0xec, // in al, dx
+        0x88, 0xc3, // mov bl, al
+        0xba, 0xf8, 0x03, // mov dx, 0x3f8
+        0xb0, 0x68, // mov al, 'h'
+        0xee, // out dx, al
+        0xb0, 0x69, // mov al, 'i'
+        0xee, // out dx, al
+    ];
+
+    let console = Console::default();
+    let output = console.output.clone();
+
+    let mut exe = Executor::with_io_bus(&program, console).unwrap();
+    while exe.execute_next().unwrap().is_some() {}
+
+    assert_eq!(exe.registers.get(&sim8086::Register::BL), 0x20);
+    assert_eq!(output.borrow().as_slice(), b"hi");
+}